@@ -0,0 +1,58 @@
+//! Decoding of large nested replies received in small reads.
+//!
+//! Run with `cargo bench --bench decode`, decoding time per megabyte
+//! must stay flat as the reply grows.
+use std::time::{Duration, Instant};
+
+use ntex::codec::Decoder;
+use ntex::util::BytesMut;
+use ntex_redis::codec::Codec;
+
+/// Size of a single socket read
+const READ_SIZE: usize = 16 * 1024;
+const ITERATIONS: u32 = 5;
+
+/// Array of `rows` arrays, each with a nested map and a few bulk strings
+fn nested_array(rows: usize) -> Vec<u8> {
+    let mut data = format!("*{}\r\n", rows).into_bytes();
+    for row in 0..rows {
+        data.extend_from_slice(b"*3\r\n");
+        data.extend_from_slice(format!("$10\r\nrow:{:06}\r\n", row % 1_000_000).as_bytes());
+        data.extend_from_slice(b"%2\r\n+field\r\n:1\r\n+other\r\n_\r\n");
+        data.extend_from_slice(b"*4\r\n$5\r\nfirst\r\n$6\r\nsecond\r\n:3\r\n,4.5\r\n");
+    }
+    data
+}
+
+fn decode(codec: &Codec, data: &[u8]) -> Duration {
+    let mut buf = BytesMut::with_capacity(data.len());
+    let mut item = None;
+    let start = Instant::now();
+    for chunk in data.chunks(READ_SIZE) {
+        buf.extend_from_slice(chunk);
+        item = codec.decode(&mut buf).unwrap();
+    }
+    let elapsed = start.elapsed();
+    assert!(item.is_some() && buf.is_empty());
+    elapsed
+}
+
+fn main() {
+    let codec = Codec::default();
+
+    for rows in [8_000, 16_000, 32_000, 64_000, 128_000] {
+        let data = nested_array(rows);
+        let elapsed = (0..ITERATIONS)
+            .map(|_| decode(&codec, &data))
+            .min()
+            .unwrap();
+        let mb = data.len() as f64 / (1024.0 * 1024.0);
+        println!(
+            "nested array {:>7} rows {:>7.2} MB: {:>9.3?} ({:.3} ms/MB)",
+            rows,
+            mb,
+            elapsed,
+            elapsed.as_secs_f64() * 1000.0 / mb
+        );
+    }
+}
@@ -0,0 +1,515 @@
+use std::collections::VecDeque;
+use std::{cell::Cell, cell::RefCell, rc::Rc, rc::Weak};
+
+use ntex::connect::{self, Address, Connect};
+use ntex::util::{ByteString, Bytes, HashMap};
+use ntex::{io::IoBoxed, service::Service};
+
+use super::cluster::request_key;
+use super::cmd::{self, commands::BulkOutputCommand, commands::HGetAllCommand, Command};
+use super::codec::{BulkString, Request, Response};
+use super::errors::{CommandError, ConnectError};
+use super::{Client, RedisConnector, SimpleClient};
+
+const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+/// Client tracking mode
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrackingMode {
+    /// Server remembers keys read by the connection
+    Default,
+    /// Server sends invalidations for all keys matching configured prefixes
+    Broadcast,
+    /// Only keys of cached reads are tracked, `CLIENT CACHING YES`
+    /// is sent before each cached read
+    OptIn,
+}
+
+/// Client side cache configuration
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    mode: TrackingMode,
+    prefixes: Vec<ByteString>,
+    noloop: bool,
+    max_size: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            mode: TrackingMode::Default,
+            prefixes: Vec::new(),
+            noloop: false,
+            max_size: 10_000,
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Create new cache configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set tracking mode, default is `TrackingMode::Default`
+    pub fn mode(mut self, mode: TrackingMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Add key prefix for broadcasting mode
+    pub fn prefix<U: AsRef<str>>(mut self, prefix: U) -> Self {
+        self.prefixes
+            .push(ByteString::from(prefix.as_ref().to_string()));
+        self
+    }
+
+    /// Do not receive invalidations for keys modified by this client
+    pub fn noloop(mut self) -> Self {
+        self.noloop = true;
+        self
+    }
+
+    /// Set maximum number of cached replies, default is 10 000
+    ///
+    /// Oldest replies are evicted first.
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = std::cmp::max(size, 1);
+        self
+    }
+}
+
+/// Client side cache statistics
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Replies served from cache
+    pub hits: u64,
+    /// Replies fetched from redis
+    pub misses: u64,
+    /// Invalidated keys
+    pub invalidations: u64,
+    /// Replies evicted because of size limit
+    pub evictions: u64,
+    /// Number of cached replies
+    pub entries: usize,
+}
+
+/// Redis client with server assisted client side caching
+///
+/// `Get`, `HGet` and `HGetAll` replies are cached locally and evicted
+/// when redis sends invalidation message for the key. Invalidations are
+/// received as RESP3 push messages, if server does not support RESP3,
+/// separate connection is subscribed to `__redis__:invalidate` channel.
+///
+/// Cache is disabled if any of the connections gets dropped.
+///
+/// ```rust
+/// use ntex_redis::{CacheConfig, CachedClient, RedisConnector, TrackingMode};
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let connector = RedisConnector::new("127.0.0.1:6379");
+///     let redis = CachedClient::connect(
+///         &connector,
+///         CacheConfig::new().mode(TrackingMode::Broadcast).prefix("config:"),
+///     )
+///     .await?;
+///
+///     // first read goes to redis, next one is served from cache
+///     let value = redis.get("config:timeout").await?;
+///     assert_eq!(redis.get("config:timeout").await?, value);
+///     assert_eq!(redis.stats().hits, 1);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct CachedClient {
+    client: Client,
+    cache: Rc<Cache>,
+    optin: bool,
+}
+
+struct Cache {
+    entries: RefCell<HashMap<Bytes, HashMap<Request, Response>>>,
+    order: RefCell<VecDeque<(Bytes, Request)>>,
+    max_size: usize,
+    epoch: Cell<u64>,
+    enabled: Cell<bool>,
+    stats: Cell<CacheStats>,
+}
+
+impl CachedClient {
+    /// Connect to redis and enable client tracking
+    pub async fn connect<A, T>(
+        connector: &RedisConnector<A, T>,
+        config: CacheConfig,
+    ) -> Result<Self, ConnectError>
+    where
+        A: Address + Clone,
+        T: Service<Connect<A>, Error = connect::ConnectError>,
+        IoBoxed: From<T::Response>,
+    {
+        let client = connector.connect().await?;
+        let cache = Rc::new(Cache {
+            entries: RefCell::new(HashMap::default()),
+            order: RefCell::new(VecDeque::new()),
+            max_size: config.max_size,
+            epoch: Cell::new(0),
+            enabled: Cell::new(true),
+            stats: Cell::new(CacheStats::default()),
+        });
+
+        let mut tracking = cmd::ClientTracking(true);
+        if client.exec(cmd::Hello(3)).await.is_ok() {
+            client.set_resp3();
+            let cache = Rc::downgrade(&cache);
+            client.on_push(move |item| {
+                if let Some(cache) = cache.upgrade() {
+                    if let [kind, keys] = item {
+                        if is_text(kind, "invalidate") {
+                            cache.invalidate(keys);
+                        }
+                    }
+                }
+            });
+        } else {
+            log::debug!("RESP3 is not supported, use redirect for invalidations");
+            let subscriber = connector.connect_simple().await?;
+            tracking = tracking.redirect(subscriber.exec(cmd::ClientId()).await?);
+            subscriber.send(cmd::Subscribe(vec![INVALIDATE_CHANNEL]))?;
+            subscriber
+                .recv_response()
+                .await?
+                .into_result()
+                .map_err(CommandError::Error)?;
+            invalidations(subscriber, Rc::downgrade(&cache));
+        }
+
+        match config.mode {
+            TrackingMode::Default => (),
+            TrackingMode::Broadcast => tracking = tracking.bcast(),
+            TrackingMode::OptIn => tracking = tracking.optin(),
+        }
+        for prefix in config.prefixes {
+            tracking = tracking.prefix(prefix);
+        }
+        if config.noloop {
+            tracking = tracking.noloop();
+        }
+        client.exec(tracking).await?;
+
+        let weak = Rc::downgrade(&cache);
+        let on_disconnect = client.on_disconnect();
+        ntex::rt::spawn(async move {
+            on_disconnect.await;
+            if let Some(cache) = weak.upgrade() {
+                cache.disable();
+            }
+        });
+
+        Ok(CachedClient {
+            client,
+            cache,
+            optin: config.mode == TrackingMode::OptIn,
+        })
+    }
+
+    /// Get the value of `key`, reply is cached
+    pub async fn get<K>(&self, key: K) -> Result<Option<Bytes>, CommandError>
+    where
+        BulkString: From<K>,
+    {
+        self.cached::<BulkOutputCommand>(cmd::Get(key).to_request())
+            .await
+    }
+
+    /// Get the value of hash `field`, reply is cached
+    pub async fn hget<K, F>(&self, key: K, field: F) -> Result<Option<Bytes>, CommandError>
+    where
+        BulkString: From<K> + From<F>,
+    {
+        self.cached::<BulkOutputCommand>(cmd::HGet(key, field).to_request())
+            .await
+    }
+
+    /// Get all fields and values of the hash, reply is cached
+    pub async fn hgetall<K>(&self, key: K) -> Result<HashMap<Bytes, Bytes>, CommandError>
+    where
+        BulkString: From<K>,
+    {
+        self.cached::<HGetAllCommand>(cmd::HGetAll(key).to_request())
+            .await
+    }
+
+    /// Execute redis command without caching
+    pub async fn exec<U>(&self, cmd: U) -> Result<U::Output, CommandError>
+    where
+        U: Command,
+    {
+        self.client.exec(cmd).await
+    }
+
+    /// Underlying client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Returns true if replies are cached
+    ///
+    /// Cache gets disabled if connection is dropped.
+    pub fn is_enabled(&self) -> bool {
+        self.cache.enabled.get()
+    }
+
+    /// Cache statistics
+    pub fn stats(&self) -> CacheStats {
+        self.cache.stats.get()
+    }
+
+    /// Remove all cached replies
+    pub fn clear(&self) {
+        self.cache.clear();
+    }
+
+    async fn cached<U: Command>(&self, req: Request) -> Result<U::Output, CommandError> {
+        let key = request_key(&req).unwrap_or_default();
+        if let Some(res) = self.cache.get(&key, &req) {
+            return U::to_output(res);
+        }
+
+        let epoch = self.cache.epoch.get();
+        let timeout = self.client.timeout();
+        let res = if self.optin {
+            let reqs = vec![cmd::ClientCaching(true).to_request(), req.clone()];
+            let mut items = self.client.send_batch(reqs, timeout).await?.into_iter();
+            if let Some(Response::Error(err)) = items.next() {
+                return Err(CommandError::Error(err));
+            }
+            items
+                .next()
+                .ok_or(CommandError::Output("Missing response", Response::Nil))?
+        } else {
+            self.client.send(req.clone(), timeout).await?
+        };
+        let res = res.into_result().map_err(CommandError::Error)?;
+
+        // key could be modified while reply was in flight
+        if self.cache.epoch.get() == epoch {
+            self.cache.insert(key, req, res.clone());
+        }
+        U::to_output(res)
+    }
+}
+
+impl Cache {
+    fn get(&self, key: &Bytes, req: &Request) -> Option<Response> {
+        if !self.enabled.get() {
+            return None;
+        }
+
+        let res = self
+            .entries
+            .borrow()
+            .get(key)
+            .and_then(|replies| replies.get(req))
+            .cloned();
+        self.update_stats(|stats| {
+            if res.is_some() {
+                stats.hits += 1
+            } else {
+                stats.misses += 1
+            }
+        });
+        res
+    }
+
+    fn insert(&self, key: Bytes, req: Request, res: Response) {
+        if !self.enabled.get() {
+            return;
+        }
+
+        let mut entries = self.entries.borrow_mut();
+        let mut order = self.order.borrow_mut();
+        let replies = entries.entry(key.clone()).or_default();
+        if replies.insert(req.clone(), res).is_none() {
+            order.push_back((key, req));
+        }
+
+        let mut evictions = 0;
+        while order.len() > self.max_size {
+            if let Some((key, req)) = order.pop_front() {
+                if let Some(replies) = entries.get_mut(&key) {
+                    replies.remove(&req);
+                    if replies.is_empty() {
+                        entries.remove(&key);
+                    }
+                }
+                evictions += 1;
+            }
+        }
+        let size = order.len();
+        self.update_stats(|stats| {
+            stats.evictions += evictions;
+            stats.entries = size;
+        });
+    }
+
+    /// Invalidate keys, nil means all keys are invalidated
+    fn invalidate(&self, keys: &Response) {
+        self.epoch.set(self.epoch.get().wrapping_add(1));
+
+        let keys = match keys {
+            Response::Array(keys) => keys,
+            _ => return self.clear(),
+        };
+        let mut entries = self.entries.borrow_mut();
+        let mut order = self.order.borrow_mut();
+        let mut invalidated = 0;
+        for key in keys {
+            let key = match key {
+                Response::Bytes(key) => key.clone(),
+                Response::String(key) => key.clone().into_bytes(),
+                _ => continue,
+            };
+            if entries.remove(&key).is_some() {
+                order.retain(|(k, _)| k != &key);
+            }
+            invalidated += 1;
+        }
+        let size = order.len();
+        self.update_stats(|stats| {
+            stats.invalidations += invalidated;
+            stats.entries = size;
+        });
+    }
+
+    fn clear(&self) {
+        self.entries.borrow_mut().clear();
+        self.order.borrow_mut().clear();
+        self.update_stats(|stats| stats.entries = 0);
+    }
+
+    fn disable(&self) {
+        log::info!("Redis connection is dropped, client side cache is disabled");
+        self.enabled.set(false);
+        self.clear();
+    }
+
+    fn update_stats<F: FnOnce(&mut CacheStats)>(&self, f: F) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
+}
+
+/// Read invalidation messages from redirect connection
+fn invalidations(subscriber: SimpleClient, cache: Weak<Cache>) {
+    ntex::rt::spawn(async move {
+        loop {
+            let item = subscriber.recv_response().await;
+            let cache = if let Some(cache) = cache.upgrade() {
+                cache
+            } else {
+                return;
+            };
+
+            match item {
+                Ok(Response::Array(item)) | Ok(Response::Push(item)) => {
+                    if let [kind, _, keys] = item.as_slice() {
+                        if is_text(kind, "message") {
+                            cache.invalidate(keys);
+                        }
+                    }
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    log::warn!("Invalidation connection failed: {}", e);
+                    cache.disable();
+                    return;
+                }
+            }
+        }
+    });
+}
+
+fn is_text(item: &Response, text: &str) -> bool {
+    match item {
+        Response::Bytes(val) => val == text.as_bytes(),
+        Response::String(val) => val == text,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(max_size: usize) -> Cache {
+        Cache {
+            entries: RefCell::new(HashMap::default()),
+            order: RefCell::new(VecDeque::new()),
+            max_size,
+            epoch: Cell::new(0),
+            enabled: Cell::new(true),
+            stats: Cell::new(CacheStats::default()),
+        }
+    }
+
+    fn get(cache: &Cache, key: &'static str) -> Option<Response> {
+        cache.get(
+            &Bytes::from_static(key.as_bytes()),
+            &cmd::Get(key).to_request(),
+        )
+    }
+
+    fn insert(cache: &Cache, key: &'static str) {
+        cache.insert(
+            Bytes::from_static(key.as_bytes()),
+            cmd::Get(key).to_request(),
+            Response::Bytes(Bytes::from_static(b"value")),
+        );
+    }
+
+    #[test]
+    fn test_cache_invalidate() {
+        let cache = cache(10);
+        assert!(get(&cache, "a").is_none());
+        insert(&cache, "a");
+        insert(&cache, "b");
+        assert!(get(&cache, "a").is_some());
+
+        cache.invalidate(&Response::Array(vec![Response::Bytes(Bytes::from_static(
+            b"a",
+        ))]));
+        assert!(get(&cache, "a").is_none());
+        assert!(get(&cache, "b").is_some());
+        assert_eq!(cache.epoch.get(), 1);
+
+        cache.invalidate(&Response::Nil);
+        assert!(get(&cache, "b").is_none());
+
+        let stats = cache.stats.get();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 3);
+        assert_eq!(stats.invalidations, 1);
+        assert_eq!(stats.entries, 0);
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let cache = cache(2);
+        insert(&cache, "a");
+        insert(&cache, "b");
+        insert(&cache, "c");
+        assert!(get(&cache, "a").is_none());
+        assert!(get(&cache, "b").is_some());
+        assert!(get(&cache, "c").is_some());
+        assert_eq!(cache.stats.get().evictions, 1);
+        assert_eq!(cache.stats.get().entries, 2);
+
+        cache.disable();
+        insert(&cache, "a");
+        assert!(get(&cache, "a").is_none());
+    }
+}
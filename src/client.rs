@@ -1,40 +1,174 @@
 use std::collections::VecDeque;
-use std::{cell::RefCell, fmt, future::Future, pin::Pin, rc::Rc, task::Context, task::Poll};
+use std::{
+    cell::Cell, cell::RefCell, fmt, future::Future, pin::Pin, rc::Rc, rc::Weak, task::Context,
+    task::Poll, time::Instant,
+};
 
+use ntex::codec::{Decoder, Encoder};
 use ntex::io::{IoBoxed, IoRef, OnDisconnect, RecvError};
-use ntex::util::{poll_fn, ready, Either, Ready};
+use ntex::time::{now, sleep, timeout_checked, Millis};
+use ntex::util::{poll_fn, ready, BytesMut, Either, Ready};
 use ntex::{channel::pool, service::Service};
 
-use super::cmd::Command;
-use super::codec::{Codec, Request, Response};
+use super::cmd::{self, commands::Commands, commands::SubscribeOutputCommand, Command};
+use super::codec::{BulkString, Codec, Request, Response};
 use super::errors::{CommandError, Error};
+use super::observer::{Observer, Outcome, Probe};
+use super::pipeline::Pipeline;
+use super::subscription::{SharedSubscriptions, Subscription};
+use super::trace::{CommandSpan, Peer};
+use super::transaction::{Transaction, TransactionResult};
 
-type Queue = Rc<RefCell<VecDeque<pool::Sender<Result<Response, Error>>>>>;
+type Queue = Rc<RefCell<VecDeque<Waiter>>>;
+type PushHandlers = Rc<RefCell<Vec<Box<dyn Fn(&[Response])>>>>;
+
+/// Response and number of bytes read for the response
+type Reply = Result<(Response, usize), Error>;
+
+enum Waiter {
+    /// Single command response
+    One(pool::Sender<Reply>),
+    /// Responses for a batch of commands, delivered as one array
+    Batch(pool::Sender<Reply>, usize, Vec<Response>, usize),
+}
+
+impl Waiter {
+    fn send(self, item: Reply) {
+        match self {
+            Waiter::One(tx) | Waiter::Batch(tx, _, _, _) => {
+                let _ = tx.send(item);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+/// Codec that records size of last encoded or decoded frame
+struct SizeCodec {
+    codec: Codec,
+    size: Cell<usize>,
+    consumed: Cell<usize>,
+}
+
+impl Encoder for SizeCodec {
+    type Item = Request;
+    type Error = Error;
+
+    fn encode(&self, msg: Request, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        let len = buf.len();
+        self.codec.encode(msg, buf)?;
+        self.size.set(buf.len() - len);
+        Ok(())
+    }
+}
+
+impl Decoder for SizeCodec {
+    type Item = Response;
+    type Error = Error;
+
+    fn decode(&self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // partially received frames are consumed by decoder
+        let len = buf.len();
+        let item = self.codec.decode(buf)?;
+        self.consumed.set(self.consumed.get() + len - buf.len());
+        if item.is_some() {
+            self.size.set(self.consumed.replace(0));
+        }
+        Ok(item)
+    }
+}
 
 #[derive(Clone)]
 /// Shared redis client
 pub struct Client {
     io: IoRef,
+    peer: Rc<Peer>,
     queue: Queue,
     disconnect: OnDisconnect,
-    pool: pool::Pool<Result<Response, Error>>,
+    pool: pool::Pool<Reply>,
+    handles: Rc<()>,
+    dedicated: bool,
+    push: PushHandlers,
+    subscriptions: SharedSubscriptions,
+    resp3: Rc<Cell<bool>>,
+    health: Rc<Health>,
+    observer: Option<Rc<dyn Observer>>,
+    timeout: Millis,
+}
+
+struct Health {
+    healthy: Cell<bool>,
+    last_activity: Cell<Instant>,
 }
 
 impl Client {
-    pub(crate) fn new(io: IoBoxed) -> Self {
+    pub(crate) fn new(
+        io: IoBoxed,
+        peer: Rc<Peer>,
+        timeout: Millis,
+        observer: Option<Rc<dyn Observer>>,
+        codec: Codec,
+    ) -> Self {
         let queue: Queue = Rc::new(RefCell::new(VecDeque::new()));
 
         // read redis response task
         let io_ref = io.get_ref();
         let queue2 = queue.clone();
+        let push: PushHandlers = Rc::new(RefCell::new(Vec::new()));
+        let push2 = push.clone();
+        let subscriptions = SharedSubscriptions::default();
+        let subscriptions2 = subscriptions.clone();
+        let health = Rc::new(Health {
+            healthy: Cell::new(true),
+            last_activity: Cell::new(now()),
+        });
+        let health2 = health.clone();
+        let peer2 = peer.clone();
         ntex::rt::spawn(async move {
+            let codec = SizeCodec {
+                codec,
+                ..Default::default()
+            };
             poll_fn(|cx| loop {
-                match ready!(io.poll_recv(&Codec, cx)) {
+                let item = ready!(io.poll_recv(&codec, cx));
+                if let Ok(ref item) = item {
+                    health2.last_activity.set(now());
+                    peer2.log_response(item);
+                }
+                match item {
+                    Ok(Response::Push(item)) => {
+                        if subscriptions2.borrow_mut().route(&item) {
+                            continue;
+                        }
+                        let handlers = push2.borrow();
+                        if handlers.is_empty() {
+                            log::debug!("Unhandled redis push message: {:?}", item);
+                        }
+                        for handler in handlers.iter() {
+                            handler(&item);
+                        }
+                        continue;
+                    }
                     Ok(item) => {
-                        if let Some(tx) = queue2.borrow_mut().pop_front() {
-                            let _ = tx.send(Ok(item));
-                        } else {
-                            log::error!("Unexpected redis response: {:?}", item);
+                        let mut queue = queue2.borrow_mut();
+                        match queue.front_mut() {
+                            Some(Waiter::Batch(_, size, items, read)) => {
+                                items.push(item);
+                                *read += codec.size.get();
+                                if items.len() >= *size {
+                                    if let Some(Waiter::Batch(tx, _, items, read)) =
+                                        queue.pop_front()
+                                    {
+                                        let _ = tx.send(Ok((Response::Array(items), read)));
+                                    }
+                                }
+                            }
+                            Some(Waiter::One(_)) => {
+                                if let Some(waiter) = queue.pop_front() {
+                                    waiter.send(Ok((item, codec.size.get())));
+                                }
+                            }
+                            None => log::error!("Unexpected redis response: {:?}", item),
                         }
                         continue;
                     }
@@ -49,16 +183,18 @@ impl Client {
                         }
                     }
                     Err(RecvError::Decoder(e)) => {
-                        if let Some(tx) = queue2.borrow_mut().pop_front() {
-                            let _ = tx.send(Err(e));
+                        if let Some(waiter) = queue2.borrow_mut().pop_front() {
+                            waiter.send(Err(e));
                         }
                         queue2.borrow_mut().clear();
+                        subscriptions2.borrow_mut().clear();
                         let _ = ready!(io.poll_shutdown(cx));
                         return Poll::Ready(());
                     }
                     Err(RecvError::PeerGone(e)) => {
                         log::info!("Redis connection is dropped: {:?}", e);
                         queue2.borrow_mut().clear();
+                        subscriptions2.borrow_mut().clear();
                         return Poll::Ready(());
                     }
                 }
@@ -72,27 +208,274 @@ impl Client {
             queue,
             disconnect,
             io: io_ref,
+            peer,
             pool: pool::new(),
+            handles: Rc::new(()),
+            dedicated: false,
+            push,
+            subscriptions,
+            resp3: Rc::new(Cell::new(false)),
+            health,
+            observer,
+            timeout,
         }
     }
 
     /// Execute redis command
+    ///
+    /// Command fails with `CommandError::Timeout` if response is not
+    /// received within default timeout configured on `RedisConnector`.
     pub fn exec<T>(&self, cmd: T) -> impl Future<Output = Result<T::Output, CommandError>>
     where
         T: Command,
     {
+        self.exec_timeout(cmd, self.timeout)
+    }
+
+    /// Execute redis command with specified timeout
+    ///
+    /// Zero timeout disables timeout. Response for timed out command is
+    /// read from connection and dropped.
+    pub fn exec_timeout<T, U>(
+        &self,
+        cmd: T,
+        timeout: U,
+    ) -> impl Future<Output = Result<T::Output, CommandError>>
+    where
+        T: Command,
+        U: Into<Millis>,
+    {
+        let fut = self.send(cmd.to_request(), timeout.into());
+
+        async move { T::to_output(fut.await?.into_result().map_err(CommandError::Error)?) }
+    }
+
+    /// Execute pipeline of redis commands
+    ///
+    /// All commands are sent in one write. Redis server errors are reported
+    /// for each command individually, protocol errors fail the whole pipeline.
+    pub fn pipeline<T>(
+        &self,
+        pipeline: Pipeline<T>,
+    ) -> impl Future<Output = Result<T::Output, CommandError>>
+    where
+        T: Commands,
+    {
+        let fut = self.send_batch(pipeline.into_requests(), self.timeout);
+
+        async move { Ok(T::to_output(&mut fut.await?.into_iter())) }
+    }
+
+    /// Execute transaction
+    ///
+    /// Commands are wrapped with `MULTI` and `EXEC` and sent in one write.
+    pub fn transaction<T>(
+        &self,
+        tx: Transaction<T>,
+    ) -> impl Future<Output = Result<TransactionResult<T::Output>, CommandError>>
+    where
+        T: Commands,
+    {
+        let fut = self.send_batch(tx.into_requests(), self.timeout);
+
+        async move { Transaction::<T>::to_output(fut.await?) }
+    }
+
+    /// Send request and wait for response
+    pub(crate) fn send(
+        &self,
+        req: Request,
+        timeout: Millis,
+    ) -> impl Future<Output = Result<Response, CommandError>> {
         let is_open = !self.io.is_closed();
-        let fut = self.call(cmd.to_request());
+        let span = CommandSpan::new(&self.peer, &req);
+        let probe = self
+            .observer
+            .as_ref()
+            .map(|observer| Probe::new(observer, Some(&req), 1, self.pending()));
+        let result = self.write(req).map(|written| {
+            let (tx, rx) = self.pool.channel();
+            self.queue.borrow_mut().push_back(Waiter::One(tx));
+            (CommandResult::new(rx), written)
+        });
 
-        async move {
-            if !is_open {
+        span.clone().instrument(async move {
+            let mut reply = (0, 0);
+            let res = if !is_open {
                 Err(CommandError::Protocol(Error::PeerGone(None)))
             } else {
-                fut.await
-                    .map_err(CommandError::Protocol)
-                    .and_then(|res| T::to_output(res.into_result().map_err(CommandError::Error)?))
+                match result {
+                    Ok((fut, written)) => {
+                        reply.0 = written;
+                        match timeout_checked(timeout, fut.reply()).await {
+                            Ok(Ok((item, read))) => {
+                                reply.1 = read;
+                                Ok(item)
+                            }
+                            Ok(Err(e)) => Err(CommandError::Protocol(e)),
+                            Err(_) => Err(CommandError::Timeout),
+                        }
+                    }
+                    Err(e) => Err(CommandError::Protocol(e)),
+                }
+            };
+            let outcome = Outcome::from_result(res.as_ref().map(std::slice::from_ref));
+            span.finish(outcome);
+            if let Some(probe) = probe {
+                probe.finish(outcome, reply.0, reply.1);
+            }
+            res
+        })
+    }
+
+    /// Send batch of requests, responses are collected into a vector
+    pub(crate) fn send_batch(
+        &self,
+        reqs: Vec<Request>,
+        timeout: Millis,
+    ) -> impl Future<Output = Result<Vec<Response>, CommandError>> {
+        let is_open = !self.io.is_closed();
+        let size = reqs.len();
+        let span = CommandSpan::batch(&self.peer, &reqs);
+        let probe = self
+            .observer
+            .as_ref()
+            .filter(|_| size != 0)
+            .map(|observer| Probe::new(observer, reqs.first(), size, self.pending()));
+        let result = if size == 0 {
+            Ok(None)
+        } else {
+            reqs.into_iter()
+                .try_fold(0, |written, req| Ok(written + self.write(req)?))
+                .map(|written| {
+                    let (tx, rx) = self.pool.channel();
+                    self.queue.borrow_mut().push_back(Waiter::Batch(
+                        tx,
+                        size,
+                        Vec::with_capacity(size),
+                        0,
+                    ));
+                    Some((CommandResult::new(rx), written))
+                })
+        };
+
+        span.clone().instrument(async move {
+            let mut reply = (0, 0);
+            let res = if !is_open {
+                Err(CommandError::Protocol(Error::PeerGone(None)))
+            } else {
+                match result {
+                    Ok(Some((fut, written))) => {
+                        reply.0 = written;
+                        match timeout_checked(timeout, fut.reply()).await {
+                            Ok(Ok((item, read))) => {
+                                reply.1 = read;
+                                match item {
+                                    Response::Array(items) => Ok(items),
+                                    item => Err(CommandError::Output(
+                                        "Expected batch of responses",
+                                        item,
+                                    )),
+                                }
+                            }
+                            Ok(Err(e)) => Err(CommandError::Protocol(e)),
+                            Err(_) => Err(CommandError::Timeout),
+                        }
+                    }
+                    Ok(None) => Ok(Vec::new()),
+                    Err(e) => Err(CommandError::Protocol(e)),
+                }
+            };
+            let outcome = Outcome::from_result(res.as_ref().map(Vec::as_slice));
+            span.finish(outcome);
+            if let Some(probe) = probe {
+                probe.finish(outcome, reply.0, reply.1);
+            }
+            res
+        })
+    }
+
+    /// Optimistic locking with `WATCH`
+    ///
+    /// Watches `keys` and executes transaction returned by `f`. If any of
+    /// the watched keys is modified before `EXEC`, whole cycle is repeated
+    /// up to `retries` times, after that `TransactionResult::Aborted` is
+    /// returned. On any error keys get unwatched and error is returned.
+    ///
+    /// Watched keys belong to a connection, so client must be opened with
+    /// [`RedisPool::dedicated`](crate::RedisPool::dedicated) and must not be
+    /// shared with other tasks, otherwise `CommandError::SharedConnection`
+    /// error is returned.
+    pub async fn watch<K, F, R, T>(
+        &self,
+        keys: K,
+        retries: usize,
+        mut f: F,
+    ) -> Result<TransactionResult<T::Output>, CommandError>
+    where
+        K: IntoIterator + Clone,
+        BulkString: From<K::Item>,
+        F: FnMut() -> R,
+        R: Future<Output = Result<Transaction<T>, CommandError>>,
+        T: Commands,
+    {
+        if !self.dedicated {
+            return Err(CommandError::SharedConnection);
+        }
+
+        let result = async {
+            for _ in 0..=retries {
+                let mut keys = keys.clone().into_iter();
+                if let Some(key) = keys.next() {
+                    self.exec(cmd::Watch(key).keys(keys)).await?;
+                }
+
+                let tx = f().await?;
+                if let TransactionResult::Committed(res) = self.transaction(tx).await? {
+                    return Ok(TransactionResult::Committed(res));
+                }
             }
+            Ok(TransactionResult::Aborted)
+        }
+        .await;
+
+        if result.is_err() {
+            // watched keys must not leak to the next user of connection
+            let _ = self.exec(cmd::Unwatch()).await;
+        }
+        result
+    }
+
+    /// Subscribe to channels on shared connection
+    ///
+    /// Subscription messages are delivered as RESP3 push messages, so
+    /// connection must use RESP3 protocol, see `RedisConnector::resp3()`.
+    /// Otherwise `CommandError::Resp3Required` error is returned.
+    ///
+    /// ```rust
+    /// use ntex_redis::{cmd, RedisConnector};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let redis = RedisConnector::new("127.0.0.1:6379").resp3().connect().await?;
+    ///
+    ///     let subscription = redis.subscribe(cmd::Subscribe(vec!["news"]))?;
+    ///     // connection is still usable for other commands
+    ///     redis.exec(cmd::Publish("news", "hello")).await?;
+    ///
+    ///     while let Some(item) = subscription.recv().await {
+    ///         println!("{:?}", item?);
+    ///         # break;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn subscribe(&self, cmd: SubscribeOutputCommand) -> Result<Subscription, CommandError> {
+        if !self.resp3.get() {
+            return Err(CommandError::Resp3Required);
         }
+        Subscription::new(self, cmd.to_request())
     }
 
     /// Delete all the keys of the currently selected DB.
@@ -105,6 +488,135 @@ impl Client {
     pub fn is_connected(&self) -> bool {
         !self.io.is_closed()
     }
+
+    /// Returns true if connection uses RESP3 protocol
+    pub fn is_resp3(&self) -> bool {
+        self.resp3.get()
+    }
+
+    /// Returns true if connection is connected and keepalive or health
+    /// check `PING` did not miss its deadline
+    pub fn is_healthy(&self) -> bool {
+        self.health.healthy.get() && self.is_connected()
+    }
+
+    /// Check connection health with `PING` command
+    ///
+    /// If `PING` response is not received within timeout, connection is
+    /// marked unhealthy and closed. Redis server errors do not affect
+    /// connection health.
+    pub async fn health_check<U: Into<Millis>>(&self, timeout: U) -> Result<(), CommandError> {
+        match self.exec_timeout(cmd::Ping(), timeout).await {
+            Ok(_) => Ok(()),
+            Err(CommandError::Error(e)) => Err(CommandError::Error(e)),
+            Err(e) => {
+                log::warn!("Redis health check failed, closing connection: {}", e);
+                self.health.healthy.set(false);
+                self.io.force_close();
+                Err(e)
+            }
+        }
+    }
+
+    /// Send `PING` when connection has no in-flight commands for
+    /// the interval
+    ///
+    /// Connection with in-flight commands is never pinged, long-running
+    /// commands like `BLPOP` must not be treated as connection failure.
+    pub(crate) fn keepalive(&self, interval: Millis, timeout: Millis) {
+        let handles = self.handles();
+        let client = self.detached();
+        ntex::rt::spawn(async move {
+            loop {
+                let idle = now().saturating_duration_since(client.health.last_activity.get());
+                let idle = Millis::from(idle);
+                if client.pending() != 0 {
+                    sleep(interval).await;
+                } else if idle < interval {
+                    sleep(Millis(interval.0 - idle.0)).await;
+                } else {
+                    let _ = client.health_check(timeout).await;
+                }
+                if handles.upgrade().is_none() || !client.is_connected() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Weak reference to client handles
+    pub(crate) fn handles(&self) -> Weak<()> {
+        Rc::downgrade(&self.handles)
+    }
+
+    /// Client for internal background tasks, is not counted as a handle
+    pub(crate) fn detached(&self) -> Client {
+        Client {
+            handles: Rc::new(()),
+            dedicated: false,
+            ..self.clone()
+        }
+    }
+
+    /// Mark client as not shared with other tasks
+    pub(crate) fn into_dedicated(self) -> Client {
+        Client {
+            dedicated: true,
+            ..self
+        }
+    }
+
+    /// Mark connection as switched to RESP3 protocol
+    pub(crate) fn set_resp3(&self) {
+        self.resp3.set(true);
+    }
+
+    /// Send request without waiting for response
+    pub(crate) fn encode(&self, req: Request) -> Result<(), CommandError> {
+        self.io
+            .encode(req, &Codec::default())
+            .map_err(CommandError::Protocol)
+    }
+
+    /// Encode request, returns size of encoded request
+    fn write(&self, req: Request) -> Result<usize, Error> {
+        self.peer.log_request(&req);
+        let codec = SizeCodec::default();
+        self.io.encode(req, &codec)?;
+        Ok(codec.size.get())
+    }
+
+    pub(crate) fn subscriptions(&self) -> &SharedSubscriptions {
+        &self.subscriptions
+    }
+
+    /// Register handler for RESP3 push messages
+    pub(crate) fn on_push<F>(&self, f: F)
+    where
+        F: Fn(&[Response]) + 'static,
+    {
+        self.push.borrow_mut().push(Box::new(f));
+    }
+
+    /// Close underlying connection
+    pub(crate) fn close(&self) {
+        self.io.close()
+    }
+
+    /// Default command timeout
+    pub(crate) fn timeout(&self) -> Millis {
+        self.timeout
+    }
+
+    /// Future that resolves when connection gets dropped
+    pub(crate) fn on_disconnect(&self) -> OnDisconnect {
+        self.io.on_disconnect()
+    }
+
+    /// Number of commands waiting for response
+    pub(crate) fn pending(&self) -> usize {
+        self.queue.borrow().len()
+    }
 }
 
 impl Service<Request> for Client {
@@ -120,13 +632,32 @@ impl Service<Request> for Client {
         }
     }
 
+    /// Send request, default timeout is not applied
     fn call(&self, req: Request) -> Self::Future<'_> {
-        if let Err(e) = self.io.encode(req, &Codec) {
-            Either::Right(Ready::Err(e))
-        } else {
-            let (tx, rx) = self.pool.channel();
-            self.queue.borrow_mut().push_back(tx);
-            Either::Left(CommandResult { rx })
+        let span = CommandSpan::new(&self.peer, &req);
+        let probe = self
+            .observer
+            .as_ref()
+            .map(|observer| Probe::new(observer, Some(&req), 1, self.pending()));
+
+        match self.write(req) {
+            Ok(written) => {
+                let (tx, rx) = self.pool.channel();
+                self.queue.borrow_mut().push_back(Waiter::One(tx));
+                Either::Left(CommandResult {
+                    rx,
+                    span: Some(span),
+                    probe: probe.map(|probe| (probe, written)),
+                })
+            }
+            Err(e) => {
+                let outcome = Outcome::from_error(&e);
+                span.finish(outcome);
+                if let Some(probe) = probe {
+                    probe.finish(outcome, 0, 0);
+                }
+                Either::Right(Ready::Err(e))
+            }
         }
     }
 }
@@ -140,16 +671,53 @@ impl fmt::Debug for Client {
 }
 
 pub struct CommandResult {
-    rx: pool::Receiver<Result<Response, Error>>,
+    rx: pool::Receiver<Reply>,
+    span: Option<CommandSpan>,
+    probe: Option<(Probe, usize)>,
 }
 
-impl Future for CommandResult {
-    type Output = Result<Response, Error>;
+impl CommandResult {
+    fn new(rx: pool::Receiver<Reply>) -> Self {
+        CommandResult {
+            rx,
+            span: None,
+            probe: None,
+        }
+    }
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    /// Wait for response and number of bytes read for the response
+    async fn reply(self) -> Reply {
+        poll_fn(|cx| self.poll_reply(cx)).await
+    }
+
+    fn poll_reply(&self, cx: &mut Context<'_>) -> Poll<Reply> {
         match ready!(self.rx.poll_recv(cx)) {
             Ok(res) => Poll::Ready(res),
             Err(_) => Poll::Ready(Err(Error::PeerGone(None))),
         }
     }
 }
+
+impl Future for CommandResult {
+    type Output = Result<Response, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let res = match this.span {
+            Some(ref span) => ready!(span.in_scope(|| this.poll_reply(cx))),
+            None => ready!(this.poll_reply(cx)),
+        };
+
+        let outcome = match res {
+            Ok((ref item, _)) => Outcome::from_result(Ok(std::slice::from_ref(item))),
+            Err(ref e) => Outcome::from_error(e),
+        };
+        if let Some(span) = this.span.take() {
+            span.finish(outcome);
+        }
+        if let Some((probe, written)) = this.probe.take() {
+            probe.finish(outcome, written, res.as_ref().map_or(0, |(_, read)| *read));
+        }
+        Poll::Ready(res.map(|(item, _)| item))
+    }
+}
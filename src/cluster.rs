@@ -0,0 +1,683 @@
+use std::{cell::Cell, cell::RefCell, future::Future, pin::Pin, rc::Rc};
+
+use ntex::connect::{self, Connect};
+use ntex::util::{Bytes, HashMap};
+use ntex::{io::IoBoxed, service::Service};
+
+use super::cmd::Command;
+use super::codec::{Request, Response};
+use super::errors::{CommandError, ConnectError};
+use super::{Client, RedisConnector};
+
+/// Number of hash slots in redis cluster
+const SLOTS: usize = 16384;
+
+/// Redis cluster client
+///
+/// Client discovers cluster topology with `CLUSTER SHARDS` (or `CLUSTER SLOTS`
+/// for servers older than 7.0) and sends each command to the node that owns
+/// hash slot of the command's key. `MOVED` and `ASK` redirects are followed,
+/// `MOVED` redirect also triggers topology refresh in background.
+///
+/// Connection setup configured on [`RedisConnector`] is used for every
+/// cluster node. Topology is discovered on first use.
+///
+/// ```rust,no_run
+/// use ntex_redis::{cmd, ClusterClient, RedisConnector};
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let redis = ClusterClient::new(RedisConnector::new("127.0.0.1:7000".to_string()))
+///         .node("127.0.0.1:7001")
+///         .node("127.0.0.1:7002");
+///
+///     redis.exec(cmd::Set("{user:1}:name", "value")).await?;
+///     let value = redis.exec(cmd::Get("{user:1}:name")).await?;
+///
+///     assert_eq!(value.unwrap(), "value");
+///     Ok(())
+/// }
+/// ```
+pub struct ClusterClient<T> {
+    connector: Rc<RedisConnector<String, T>>,
+    seeds: Vec<String>,
+    nodes: Rc<RefCell<HashMap<String, Client>>>,
+    slots: Rc<RefCell<Vec<Option<Rc<str>>>>>,
+    refreshing: Rc<Cell<bool>>,
+    max_redirects: usize,
+}
+
+impl<T> Clone for ClusterClient<T> {
+    fn clone(&self) -> Self {
+        ClusterClient {
+            connector: self.connector.clone(),
+            seeds: self.seeds.clone(),
+            nodes: self.nodes.clone(),
+            slots: self.slots.clone(),
+            refreshing: self.refreshing.clone(),
+            max_redirects: self.max_redirects,
+        }
+    }
+}
+
+impl<T> ClusterClient<T>
+where
+    T: Service<Connect<String>, Error = connect::ConnectError> + 'static,
+    IoBoxed: From<T::Response>,
+{
+    /// Create new cluster client
+    ///
+    /// Connector address is used as first seed node.
+    pub fn new(connector: RedisConnector<String, T>) -> Self {
+        ClusterClient {
+            seeds: vec![connector.address().clone()],
+            connector: Rc::new(connector),
+            nodes: Rc::new(RefCell::new(HashMap::default())),
+            slots: Rc::new(RefCell::new(Vec::new())),
+            refreshing: Rc::new(Cell::new(false)),
+            max_redirects: 5,
+        }
+    }
+
+    /// Add seed node used for topology discovery
+    pub fn node<U: Into<String>>(mut self, address: U) -> Self {
+        self.seeds.push(address.into());
+        self
+    }
+
+    /// Set maximum number of redirects for one command, default is 5
+    pub fn max_redirects(mut self, max: usize) -> Self {
+        self.max_redirects = max;
+        self
+    }
+
+    /// Execute redis command on the node that owns command's key
+    pub async fn exec<U>(&self, cmd: U) -> Result<U::Output, ConnectError>
+    where
+        U: Command,
+    {
+        let res = self.send(cmd.to_request()).await?;
+        Ok(U::to_output(
+            res.into_result().map_err(CommandError::Error)?,
+        )?)
+    }
+
+    /// Reload cluster topology
+    ///
+    /// Known nodes and seed nodes are queried one by one, until one
+    /// of them returns topology.
+    pub async fn refresh(&self) -> Result<(), ConnectError> {
+        let mut candidates: Vec<String> = self.nodes.borrow().keys().cloned().collect();
+        for seed in &self.seeds {
+            if !candidates.contains(seed) {
+                candidates.push(seed.clone());
+            }
+        }
+
+        let mut error = None;
+        for address in candidates {
+            match self.query_topology(&address).await {
+                Ok(ranges) => {
+                    self.update(ranges);
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::debug!("Cannot load cluster topology from {}: {}", address, e);
+                    error = Some(e);
+                }
+            }
+        }
+        Err(error.unwrap_or_else(|| {
+            CommandError::Output("Cluster nodes are not configured", Response::Nil).into()
+        }))
+    }
+
+    /// Get client for cluster node, connection is opened if needed
+    pub async fn get(&self, address: &str) -> Result<Client, ConnectError> {
+        if let Some(client) = self.nodes.borrow().get(address) {
+            if client.is_connected() {
+                return Ok(client.clone());
+            }
+        }
+
+        let client = self.connector.connect_to(address.to_string()).await?;
+        self.nodes
+            .borrow_mut()
+            .insert(address.to_string(), client.clone());
+        Ok(client)
+    }
+
+    async fn send(&self, req: Request) -> Result<Response, ConnectError> {
+        if self.slots.borrow().is_empty() {
+            self.refresh().await?;
+        }
+
+        let mut address = self.address_for(request_slot(&req));
+        let mut asking = false;
+        let mut redirects = 0;
+
+        loop {
+            let client = match self.get(&address).await {
+                Ok(client) => client,
+                Err(e) => {
+                    self.refresh_background();
+                    return Err(e);
+                }
+            };
+
+            let res = if asking {
+                let reqs = vec![
+                    Request::Array(vec![Request::from_static("ASKING")]),
+                    req.clone(),
+                ];
+                client.send_batch(reqs, client.timeout()).await?.pop()
+            } else {
+                Some(client.send(req.clone(), client.timeout()).await?)
+            };
+            let res = res.ok_or(CommandError::Output("Missing response", Response::Nil))?;
+
+            if let Response::Error(ref err) = res {
+                if redirects < self.max_redirects {
+                    match Redirect::parse(err, &address) {
+                        Some(Redirect::Moved(slot, node)) => {
+                            log::debug!("Slot {} is moved to {}", slot, node);
+                            if let Some(entry) = self.slots.borrow_mut().get_mut(slot as usize) {
+                                *entry = Some(node.as_str().into());
+                            }
+                            self.refresh_background();
+                            address = node;
+                            asking = false;
+                            redirects += 1;
+                            continue;
+                        }
+                        Some(Redirect::Ask(_, node)) => {
+                            address = node;
+                            asking = true;
+                            redirects += 1;
+                            continue;
+                        }
+                        None => (),
+                    }
+                }
+            }
+            return Ok(res);
+        }
+    }
+
+    /// Node address for hash slot, commands without keys go to any node
+    fn address_for(&self, slot: Option<u16>) -> String {
+        let slots = self.slots.borrow();
+        slot.and_then(|slot| slots.get(slot as usize).cloned().flatten())
+            .or_else(|| slots.iter().flatten().next().cloned())
+            .map(|addr| addr.to_string())
+            .unwrap_or_else(|| self.seeds[0].clone())
+    }
+
+    async fn query_topology(&self, address: &str) -> Result<Vec<SlotRange>, ConnectError> {
+        let client = self.get(address).await?;
+        let host = host(address);
+
+        let req = Request::Array(vec![
+            Request::from_static("CLUSTER"),
+            Request::from_static("SHARDS"),
+        ]);
+        let ranges = match client.send(req, client.timeout()).await? {
+            Response::Error(_) => {
+                let req = Request::Array(vec![
+                    Request::from_static("CLUSTER"),
+                    Request::from_static("SLOTS"),
+                ]);
+                let res = client
+                    .send(req, client.timeout())
+                    .await?
+                    .into_result()
+                    .map_err(CommandError::Error)?;
+                parse_slots(&res, host)
+                    .ok_or(CommandError::Output("Cannot parse CLUSTER SLOTS", res))?
+            }
+            res => parse_shards(&res, host)
+                .ok_or(CommandError::Output("Cannot parse CLUSTER SHARDS", res))?,
+        };
+
+        if ranges.is_empty() {
+            Err(CommandError::Output("Cluster slots are not assigned", Response::Nil).into())
+        } else {
+            Ok(ranges)
+        }
+    }
+
+    /// Replace slots table, connections to removed nodes are dropped
+    fn update(&self, ranges: Vec<SlotRange>) {
+        let mut slots: Vec<Option<Rc<str>>> = vec![None; SLOTS];
+        for range in ranges {
+            let node: Rc<str> = range.node.as_str().into();
+            for slot in range.start..=std::cmp::min(range.end, (SLOTS - 1) as u16) {
+                slots[slot as usize] = Some(node.clone());
+            }
+        }
+
+        self.nodes
+            .borrow_mut()
+            .retain(|addr, _| slots.iter().flatten().any(|node| node.as_ref() == addr));
+        *self.slots.borrow_mut() = slots;
+    }
+
+    fn refresh_background(&self) {
+        if self.refreshing.replace(true) {
+            return;
+        }
+
+        let client = self.clone();
+        ntex::rt::spawn(async move {
+            if let Err(e) = client.refresh().await {
+                log::warn!("Cannot refresh cluster topology: {}", e);
+            }
+            client.refreshing.set(false);
+        });
+    }
+}
+
+impl<T> Service<Request> for ClusterClient<T>
+where
+    T: Service<Connect<String>, Error = connect::ConnectError> + 'static,
+    IoBoxed: From<T::Response>,
+{
+    type Response = Response;
+    type Error = ConnectError;
+    type Future<'f>
+        = Pin<Box<dyn Future<Output = Result<Response, ConnectError>> + 'f>>
+    where
+        Self: 'f;
+
+    fn call(&self, req: Request) -> Self::Future<'_> {
+        Box::pin(self.send(req))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Redirect {
+    Moved(u16, String),
+    Ask(u16, String),
+}
+
+impl Redirect {
+    /// Parse `MOVED <slot> <addr>` or `ASK <slot> <addr>` error
+    ///
+    /// Empty host in address refers to the node that sent redirect.
+    fn parse(err: &str, current: &str) -> Option<Redirect> {
+        let mut parts = err.split(' ');
+        let kind = parts.next()?;
+        let slot = parts.next()?.parse().ok()?;
+        let node = parts.next()?;
+        let node = if node.starts_with(':') {
+            format!("{}{}", host(current), node)
+        } else {
+            node.to_string()
+        };
+
+        match kind {
+            "MOVED" => Some(Redirect::Moved(slot, node)),
+            "ASK" => Some(Redirect::Ask(slot, node)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct SlotRange {
+    start: u16,
+    end: u16,
+    node: String,
+}
+
+/// Host part of `host:port` address
+fn host(address: &str) -> &str {
+    address
+        .rsplit_once(':')
+        .map(|(host, _)| host)
+        .unwrap_or(address)
+}
+
+/// Parse `CLUSTER SLOTS` response
+fn parse_slots(res: &Response, host: &str) -> Option<Vec<SlotRange>> {
+    let mut ranges = Vec::new();
+    for item in array(res)? {
+        let item = array(item)?;
+        let master = array(item.get(2)?)?;
+        let ip = text(master.first()?)?;
+        let ip = if ip.is_empty() || ip == "?" { host } else { ip };
+        ranges.push(SlotRange {
+            start: integer(item.first()?)? as u16,
+            end: integer(item.get(1)?)? as u16,
+            node: format!("{}:{}", ip, integer(master.get(1)?)?),
+        });
+    }
+    Some(ranges)
+}
+
+/// Parse `CLUSTER SHARDS` response
+fn parse_shards(res: &Response, host: &str) -> Option<Vec<SlotRange>> {
+    let mut ranges = Vec::new();
+    for shard in array(res)? {
+        let shard = fields(shard)?;
+        let slots = array(field(&shard, "slots")?)?;
+
+        let master = array(field(&shard, "nodes")?)?
+            .iter()
+            .filter_map(fields)
+            .find(|node| {
+                field(node, "role").and_then(text) == Some("master")
+                    && field(node, "health").and_then(text) != Some("fail")
+            });
+        let master = match master {
+            Some(master) => master,
+            None => continue,
+        };
+
+        let ip = field(&master, "endpoint")
+            .and_then(text)
+            .filter(|ip| !ip.is_empty() && *ip != "?")
+            .or_else(|| field(&master, "ip").and_then(text))
+            .filter(|ip| !ip.is_empty())
+            .unwrap_or(host);
+        let port = integer(field(&master, "port")?)?;
+        let node = format!("{}:{}", ip, port);
+
+        for pair in slots.chunks(2) {
+            if let [start, end] = pair {
+                ranges.push(SlotRange {
+                    start: integer(start)? as u16,
+                    end: integer(end)? as u16,
+                    node: node.clone(),
+                });
+            }
+        }
+    }
+    Some(ranges)
+}
+
+fn array(res: &Response) -> Option<&[Response]> {
+    match res {
+        Response::Array(items) | Response::Set(items) => Some(items),
+        _ => None,
+    }
+}
+
+fn text(res: &Response) -> Option<&str> {
+    match res {
+        Response::String(s) => Some(s),
+        Response::Bytes(b) | Response::Verbatim(_, b) => std::str::from_utf8(b).ok(),
+        _ => None,
+    }
+}
+
+fn integer(res: &Response) -> Option<i64> {
+    match res {
+        Response::Integer(val) => Some(*val),
+        res => text(res)?.parse().ok(),
+    }
+}
+
+/// RESP3 map or flat key-value array to list of fields
+fn fields(res: &Response) -> Option<Vec<(&str, &Response)>> {
+    if let Response::Map(pairs) = res {
+        return pairs
+            .iter()
+            .map(|(key, val)| Some((text(key)?, val)))
+            .collect();
+    }
+    array(res)?
+        .chunks(2)
+        .map(|pair| match pair {
+            [key, val] => Some((text(key)?, val)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn field<'a>(fields: &[(&str, &'a Response)], name: &str) -> Option<&'a Response> {
+    fields
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, val)| *val)
+}
+
+/// Hash slot of the key
+///
+/// If key contains `{...}` with at least one character between braces,
+/// only that part of the key is hashed.
+pub(crate) fn key_slot(key: &[u8]) -> u16 {
+    let key = key
+        .iter()
+        .position(|b| *b == b'{')
+        .and_then(|start| {
+            key[start + 1..]
+                .iter()
+                .position(|b| *b == b'}')
+                .filter(|len| *len > 0)
+                .map(|len| &key[start + 1..start + 1 + len])
+        })
+        .unwrap_or(key);
+    crc16(key) % SLOTS as u16
+}
+
+/// CRC16 (XMODEM) used by redis cluster
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Hash slot of the request, `None` for commands without keys
+fn request_slot(req: &Request) -> Option<u16> {
+    request_key(req).map(|key| key_slot(&key))
+}
+
+/// First key of the request
+pub(crate) fn request_key(req: &Request) -> Option<Bytes> {
+    let args = match req {
+        Request::Array(args) => args,
+        _ => return None,
+    };
+    let name = args.first()?.to_bytes()?.to_ascii_uppercase();
+
+    let pos = match name.as_slice() {
+        b"PING" | b"ECHO" | b"AUTH" | b"HELLO" | b"SELECT" | b"INFO" | b"KEYS" | b"SCAN"
+        | b"DBSIZE" | b"FLUSHDB" | b"FLUSHALL" | b"MULTI" | b"EXEC" | b"DISCARD" | b"UNWATCH"
+        | b"PUBLISH" | b"SUBSCRIBE" | b"PSUBSCRIBE" | b"UNSUBSCRIBE" | b"PUNSUBSCRIBE"
+        | b"CLIENT" | b"CLUSTER" | b"CONFIG" | b"COMMAND" | b"RESET" | b"TIME" | b"SCRIPT"
+        | b"FUNCTION" | b"ROLE" | b"ASKING" | b"READONLY" | b"READWRITE" | b"QUIT" => return None,
+        // EVAL script numkeys key [key ...]
+        b"EVAL" | b"EVALSHA" | b"EVAL_RO" | b"EVALSHA_RO" | b"FCALL" | b"FCALL_RO" => {
+            let numkeys = args.get(2)?.to_bytes()?;
+            if numkeys.as_ref() == b"0" {
+                return None;
+            }
+            3
+        }
+        // XREAD [COUNT count] [BLOCK ms] STREAMS key [key ...] id [id ...]
+        b"XREAD" | b"XREADGROUP" => {
+            args.iter().position(|arg| {
+                arg.to_bytes()
+                    .map(|arg| arg.eq_ignore_ascii_case(b"STREAMS"))
+                    .unwrap_or(false)
+            })? + 1
+        }
+        _ => 1,
+    };
+    args.get(pos)?.to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, cmd};
+    use ntex::util::ByteString;
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"bar"), 5061);
+        assert_eq!(
+            key_slot(b"{user1000}.following"),
+            key_slot(b"{user1000}.followers")
+        );
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+        assert_eq!(key_slot(b"foo{}{bar}"), crc16(b"foo{}{bar}") % 16384);
+        assert_eq!(key_slot(b"foo{{bar}}zap"), key_slot(b"{bar"));
+        assert_eq!(key_slot(b"foo{bar}{zap}"), key_slot(b"bar"));
+        assert_eq!(key_slot(b"{foo"), crc16(b"{foo") % 16384);
+    }
+
+    #[test]
+    fn test_request_slot() {
+        let slot = key_slot(b"foo");
+        assert_eq!(request_slot(&cmd::Get("foo").to_request()), Some(slot));
+        assert_eq!(
+            request_slot(&cmd::Set("foo", "bar").to_request()),
+            Some(slot)
+        );
+        assert_eq!(request_slot(&cmd::Ping().to_request()), None);
+        assert_eq!(request_slot(&array!["EVAL", "return 1", "0"]), None);
+        assert_eq!(
+            request_slot(&array!["EVAL", "return 1", "1", "foo"]),
+            Some(slot)
+        );
+        assert_eq!(
+            request_slot(&array!["XREAD", "COUNT", "1", "streams", "foo", "0"]),
+            Some(slot)
+        );
+    }
+
+    #[test]
+    fn test_redirect() {
+        assert_eq!(
+            Redirect::parse("MOVED 3999 127.0.0.1:6381", "127.0.0.1:6379"),
+            Some(Redirect::Moved(3999, "127.0.0.1:6381".to_string()))
+        );
+        assert_eq!(
+            Redirect::parse("ASK 3999 :6381", "10.0.0.1:6379"),
+            Some(Redirect::Ask(3999, "10.0.0.1:6381".to_string()))
+        );
+        assert_eq!(
+            Redirect::parse("ERR unknown command", "127.0.0.1:6379"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_slots() {
+        let res = Response::Array(vec![Response::Array(vec![
+            Response::Integer(0),
+            Response::Integer(5460),
+            Response::Array(vec![
+                Response::Bytes(Bytes::from_static(b"")),
+                Response::Integer(30001),
+                Response::Bytes(Bytes::from_static(
+                    b"09dbe9720cda62f7865eabc5fd8857c5d2678366",
+                )),
+            ]),
+        ])]);
+        assert_eq!(
+            parse_slots(&res, "127.0.0.1").unwrap(),
+            vec![SlotRange {
+                start: 0,
+                end: 5460,
+                node: "127.0.0.1:30001".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_shards() {
+        let bytes = |s: &'static str| Response::Bytes(Bytes::from_static(s.as_bytes()));
+        let node = |port, role| {
+            Response::Array(vec![
+                bytes("port"),
+                Response::Integer(port),
+                bytes("ip"),
+                bytes("10.0.0.1"),
+                bytes("endpoint"),
+                bytes("10.0.0.1"),
+                bytes("role"),
+                bytes(role),
+                bytes("health"),
+                bytes("online"),
+            ])
+        };
+        let res = Response::Array(vec![Response::Array(vec![
+            bytes("slots"),
+            Response::Array(vec![
+                Response::Integer(0),
+                Response::Integer(100),
+                Response::Integer(200),
+                Response::Integer(300),
+            ]),
+            bytes("nodes"),
+            Response::Array(vec![node(30004, "replica"), node(30001, "master")]),
+        ])]);
+        assert_eq!(
+            parse_shards(&res, "127.0.0.1").unwrap(),
+            vec![
+                SlotRange {
+                    start: 0,
+                    end: 100,
+                    node: "10.0.0.1:30001".to_string()
+                },
+                SlotRange {
+                    start: 200,
+                    end: 300,
+                    node: "10.0.0.1:30001".to_string()
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_shards_resp3() {
+        let string = |s: &'static str| Response::String(ByteString::from_static(s));
+        let node = |port, role: &'static str| {
+            Response::Map(vec![
+                (string("port"), Response::Integer(port)),
+                (string("ip"), string("10.0.0.1")),
+                (string("endpoint"), string("10.0.0.2")),
+                (
+                    string("role"),
+                    Response::Verbatim(
+                        ByteString::from_static("txt"),
+                        Bytes::from_static(role.as_bytes()),
+                    ),
+                ),
+                (string("health"), string("online")),
+            ])
+        };
+        let res = Response::Array(vec![Response::Map(vec![
+            (
+                string("slots"),
+                Response::Set(vec![Response::Integer(0), Response::Integer(16383)]),
+            ),
+            (
+                string("nodes"),
+                Response::Array(vec![node(30004, "replica"), node(30001, "master")]),
+            ),
+        ])]);
+        assert_eq!(
+            parse_shards(&res, "127.0.0.1").unwrap(),
+            vec![SlotRange {
+                start: 0,
+                end: 16383,
+                node: "10.0.0.2:30001".to_string()
+            }]
+        );
+    }
+}
@@ -14,6 +14,19 @@ where
 
 pub struct AuthCommand(Request);
 
+impl AuthCommand {
+    /// Authenticate as specified ACL user, sends `AUTH <username> <password>`
+    pub fn username<T>(mut self, username: T) -> Self
+    where
+        BulkString: From<T>,
+    {
+        if let Request::Array(ref mut args) = self.0 {
+            args.insert(1, Request::BulkString(username.into()));
+        }
+        self
+    }
+}
+
 impl Command for AuthCommand {
     type Output = bool;
 
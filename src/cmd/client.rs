@@ -0,0 +1,120 @@
+use super::{utils, Command, CommandError};
+use crate::codec::{BulkString, Request, Response};
+
+/// CLIENT ID redis command
+///
+/// Returns the ID of the current connection.
+pub fn ClientId() -> utils::IntOutputCommand {
+    utils::IntOutputCommand(Request::Array(vec![
+        Request::from_static("CLIENT"),
+        Request::from_static("ID"),
+    ]))
+}
+
+/// CLIENT SETNAME redis command
+///
+/// Assigns a name to the current connection.
+pub fn ClientSetName<T>(name: T) -> ClientSetNameCommand
+where
+    BulkString: From<T>,
+{
+    ClientSetNameCommand(Request::Array(vec![
+        Request::from_static("CLIENT"),
+        Request::from_static("SETNAME"),
+        Request::BulkString(name.into()),
+    ]))
+}
+
+pub struct ClientSetNameCommand(Request);
+
+impl Command for ClientSetNameCommand {
+    type Output = bool;
+
+    fn to_request(self) -> Request {
+        self.0
+    }
+
+    fn to_output(val: Response) -> Result<Self::Output, CommandError> {
+        match val {
+            Response::String(val) => Ok(val == "OK"),
+            _ => Err(CommandError::Output("Unexpected value", val)),
+        }
+    }
+}
+
+/// CLIENT TRACKING redis command
+///
+/// Enables or disables server assisted client side caching
+/// for the current connection.
+pub fn ClientTracking(on: bool) -> ClientTrackingCommand {
+    ClientTrackingCommand(vec![
+        Request::from_static("CLIENT"),
+        Request::from_static("TRACKING"),
+        Request::from_static(if on { "ON" } else { "OFF" }),
+    ])
+}
+
+/// CLIENT CACHING redis command
+///
+/// Controls tracking of the next command in `OPTIN` or `OPTOUT` mode.
+pub fn ClientCaching(yes: bool) -> ClientTrackingCommand {
+    ClientTrackingCommand(vec![
+        Request::from_static("CLIENT"),
+        Request::from_static("CACHING"),
+        Request::from_static(if yes { "YES" } else { "NO" }),
+    ])
+}
+
+pub struct ClientTrackingCommand(Vec<Request>);
+
+impl ClientTrackingCommand {
+    /// Send invalidation messages to the connection with specified id
+    pub fn redirect(mut self, id: i64) -> Self {
+        self.0.push(Request::from_static("REDIRECT"));
+        self.0.push(Request::BulkInteger(id));
+        self
+    }
+
+    /// Track keys with specified prefix, used with broadcasting mode
+    pub fn prefix<T>(mut self, prefix: T) -> Self
+    where
+        BulkString: From<T>,
+    {
+        self.0.push(Request::from_static("PREFIX"));
+        self.0.push(prefix.into());
+        self
+    }
+
+    /// Enable broadcasting mode
+    pub fn bcast(mut self) -> Self {
+        self.0.push(Request::from_static("BCAST"));
+        self
+    }
+
+    /// Track keys only after `CLIENT CACHING YES` command
+    pub fn optin(mut self) -> Self {
+        self.0.push(Request::from_static("OPTIN"));
+        self
+    }
+
+    /// Do not send invalidations for keys modified by this connection
+    pub fn noloop(mut self) -> Self {
+        self.0.push(Request::from_static("NOLOOP"));
+        self
+    }
+}
+
+impl Command for ClientTrackingCommand {
+    type Output = bool;
+
+    fn to_request(self) -> Request {
+        Request::Array(self.0)
+    }
+
+    fn to_output(val: Response) -> Result<Self::Output, CommandError> {
+        match val {
+            Response::String(val) => Ok(val == "OK"),
+            _ => Err(CommandError::Output("Unexpected value", val)),
+        }
+    }
+}
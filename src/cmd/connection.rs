@@ -1,7 +1,9 @@
-use ntex::util::ByteString;
+use std::convert::TryFrom;
+
+use ntex::util::{ByteString, HashMap};
 
 use super::{Command, CommandError};
-use crate::codec::{Request, Response};
+use crate::codec::{BulkString, Request, Response};
 
 /// SELECT redis command
 ///
@@ -127,3 +129,132 @@ impl Command for ResetCommand {
         }
     }
 }
+
+/// ROLE redis command
+///
+/// Returns role of the instance: `master`, `slave` or `sentinel`.
+///
+/// ```rust
+/// use ntex_redis::{cmd, RedisConnector};
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let redis = RedisConnector::new("127.0.0.1:6379").connect().await?;
+///
+///     let role = redis.exec(cmd::Role()).await?;
+///
+///     assert_eq!(&role, "master");
+///
+///     Ok(())
+/// }
+/// ```
+pub fn Role() -> RoleCommand {
+    RoleCommand(Request::Array(vec![Request::from_static("ROLE")]))
+}
+
+pub struct RoleCommand(Request);
+
+impl Command for RoleCommand {
+    type Output = ByteString;
+
+    fn to_request(self) -> Request {
+        self.0
+    }
+
+    fn to_output(val: Response) -> Result<Self::Output, CommandError> {
+        match val {
+            Response::Array(ref items) => match items.first() {
+                Some(Response::Bytes(role)) => ByteString::try_from(role.clone())
+                    .map_err(|_| CommandError::Output("Cannot parse role", val)),
+                Some(Response::String(role)) => Ok(role.clone()),
+                _ => Err(CommandError::Output("Cannot parse role", val)),
+            },
+            Response::Error(val) => Err(CommandError::Error(val)),
+            _ => Err(CommandError::Output("Unknown response", val)),
+        }
+    }
+}
+
+/// HELLO redis command
+///
+/// Switch connection to specified protocol version. Returns server
+/// properties, like `server`, `version` and `proto`.
+///
+/// ```rust
+/// use ntex_redis::{cmd, RedisConnector};
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let redis = RedisConnector::new("127.0.0.1:6379").connect_simple().await?;
+///
+///     let props = redis.exec(cmd::Hello(2)).await?;
+///     assert!(props.contains_key("version"));
+///
+///     Ok(())
+/// }
+/// ```
+pub fn Hello(protover: u8) -> HelloCommand {
+    HelloCommand(Request::Array(vec![
+        Request::from_static("HELLO"),
+        Request::BulkInteger(protover as i64),
+    ]))
+}
+
+pub struct HelloCommand(Request);
+
+impl HelloCommand {
+    /// Authenticate as ACL user with the same round-trip
+    pub fn auth<U, P>(mut self, username: U, password: P) -> Self
+    where
+        BulkString: From<U> + From<P>,
+    {
+        if let Request::Array(ref mut args) = self.0 {
+            args.push(Request::from_static("AUTH"));
+            args.push(Request::BulkString(username.into()));
+            args.push(Request::BulkString(password.into()));
+        }
+        self
+    }
+
+    /// Set connection name
+    pub fn setname<T>(mut self, name: T) -> Self
+    where
+        BulkString: From<T>,
+    {
+        if let Request::Array(ref mut args) = self.0 {
+            args.push(Request::from_static("SETNAME"));
+            args.push(Request::BulkString(name.into()));
+        }
+        self
+    }
+}
+
+impl Command for HelloCommand {
+    type Output = HashMap<ByteString, Response>;
+
+    fn to_request(self) -> Request {
+        self.0
+    }
+
+    fn to_output(val: Response) -> Result<Self::Output, CommandError> {
+        let items = match val {
+            Response::Map(items) => items,
+            Response::Array(items) => {
+                let mut pairs = Vec::with_capacity(items.len() / 2);
+                let mut items = items.into_iter();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    pairs.push((key, value));
+                }
+                pairs
+            }
+            Response::Error(val) => return Err(CommandError::Error(val)),
+            _ => return Err(CommandError::Output("Unknown response", val)),
+        };
+
+        let mut props = HashMap::default();
+        for (key, value) in items {
+            props.insert(ByteString::try_from(key)?, value);
+        }
+        Ok(props)
+    }
+}
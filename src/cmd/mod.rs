@@ -5,16 +5,20 @@ use super::codec::{Request, Response};
 use super::errors::CommandError;
 
 mod auth;
+mod client;
 mod connection;
 mod hashes;
 mod keys;
 mod lists;
 mod pubsub;
+mod sentinel;
 mod strings;
+mod transactions;
 mod utils;
 
 pub use self::auth::Auth;
-pub use self::connection::{Ping, Reset, Select};
+pub use self::client::{ClientCaching, ClientId, ClientSetName, ClientTracking};
+pub use self::connection::{Hello, Ping, Reset, Role, Select};
 pub use self::hashes::{HDel, HGet, HGetAll, HIncrBy, HLen, HSet};
 pub use self::keys::{Del, Exists, Expire, ExpireAt, Keys, Ttl, TtlResult};
 pub use self::lists::{LIndex, LPop, LPush, RPop, RPush};
@@ -22,7 +26,9 @@ pub use self::pubsub::{
     PSubscribe, PUnSubscribe, Publish, SPublish, SSubscribe, SUnSubscribe, Subscribe,
     SubscribeItem, UnSubscribe,
 };
+pub use self::sentinel::SentinelGetMasterAddr;
 pub use self::strings::{Get, IncrBy, Set};
+pub use self::transactions::{Discard, Exec, Multi, Unwatch, Watch};
 
 /// Trait implemented by types that can be used as redis commands
 pub trait Command {
@@ -39,10 +45,14 @@ pub trait Command {
 pub mod commands {
     //! Command implementations
     pub use super::auth::AuthCommand;
+    pub use super::client::{ClientSetNameCommand, ClientTrackingCommand};
     pub use super::hashes::{HDelCommand, HGetAllCommand, HSetCommand};
     pub use super::keys::{KeysCommand, KeysPatternCommand, TtlCommand};
     pub use super::lists::LPushCommand;
     pub use super::pubsub::{PubSubCommand, SubscribeOutputCommand};
+    pub use super::sentinel::SentinelMasterAddrCommand;
     pub use super::strings::SetCommand;
+    pub use super::transactions::{ExecCommand, TransactionCommand, WatchCommand};
     pub use super::utils::{BulkOutputCommand, IntOutputCommand};
+    pub use crate::pipeline::{Append, Commands};
 }
@@ -16,7 +16,7 @@ const TYPE_PMESSAGE: Bytes = Bytes::from_static(b"pmessage");
 
 pub trait PubSubCommand {}
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SubscribeItem {
     Subscribed(Bytes),
     UnSubscribed(Bytes),
@@ -46,7 +46,7 @@ impl TryFrom<Response> for SubscribeItem {
 
     fn try_from(val: Response) -> Result<Self, Self::Error> {
         let (mtype, pattern, channel, payload) = match val {
-            Response::Array(ary) => match ary.len() {
+            Response::Array(ary) | Response::Push(ary) => match ary.len() {
                 // subscribe or ssubscribe message
                 3 => {
                     let mut ary_iter = ary.into_iter();
@@ -0,0 +1,57 @@
+use std::convert::TryFrom;
+
+use ntex::util::ByteString;
+
+use super::{Command, CommandError};
+use crate::codec::{BulkString, Request, Response};
+
+/// SENTINEL GET-MASTER-ADDR-BY-NAME redis command
+///
+/// Returns address of the master with specified name, as seen by
+/// the sentinel. `None` is returned for unknown master.
+pub fn SentinelGetMasterAddr<T>(name: T) -> SentinelMasterAddrCommand
+where
+    BulkString: From<T>,
+{
+    SentinelMasterAddrCommand(Request::Array(vec![
+        Request::from_static("SENTINEL"),
+        Request::from_static("GET-MASTER-ADDR-BY-NAME"),
+        Request::BulkString(name.into()),
+    ]))
+}
+
+pub struct SentinelMasterAddrCommand(Request);
+
+impl Command for SentinelMasterAddrCommand {
+    type Output = Option<(ByteString, u16)>;
+
+    fn to_request(self) -> Request {
+        self.0
+    }
+
+    fn to_output(val: Response) -> Result<Self::Output, CommandError> {
+        match val {
+            Response::Nil => Ok(None),
+            Response::Array(ref items) if items.len() == 2 => {
+                let host = match items[0] {
+                    Response::Bytes(ref host) => ByteString::try_from(host.clone()).ok(),
+                    Response::String(ref host) => Some(host.clone()),
+                    _ => None,
+                };
+                let port = match items[1] {
+                    Response::Bytes(ref port) => std::str::from_utf8(port)
+                        .ok()
+                        .and_then(|port| port.parse().ok()),
+                    Response::Integer(port) => u16::try_from(port).ok(),
+                    _ => None,
+                };
+                match (host, port) {
+                    (Some(host), Some(port)) => Ok(Some((host, port))),
+                    _ => Err(CommandError::Output("Cannot parse master address", val)),
+                }
+            }
+            Response::Error(val) => Err(CommandError::Error(val)),
+            _ => Err(CommandError::Output("Unknown response", val)),
+        }
+    }
+}
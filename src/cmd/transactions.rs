@@ -0,0 +1,114 @@
+use super::{Command, CommandError};
+use crate::codec::{BulkString, Request, Response};
+
+/// MULTI redis command
+///
+/// Marks the start of a transaction block.
+pub fn Multi() -> TransactionCommand {
+    TransactionCommand(Request::Array(vec![Request::from_static("MULTI")]))
+}
+
+/// EXEC redis command
+///
+/// Executes all previously queued commands in a transaction. Command
+/// returns `None` if transaction is aborted because of watched keys.
+pub fn Exec() -> ExecCommand {
+    ExecCommand(Request::Array(vec![Request::from_static("EXEC")]))
+}
+
+/// DISCARD redis command
+///
+/// Flushes all previously queued commands in a transaction.
+pub fn Discard() -> TransactionCommand {
+    TransactionCommand(Request::Array(vec![Request::from_static("DISCARD")]))
+}
+
+/// WATCH redis command
+///
+/// Marks the given keys to be watched for conditional execution
+/// of a transaction.
+pub fn Watch<T>(key: T) -> WatchCommand
+where
+    BulkString: From<T>,
+{
+    WatchCommand(vec![
+        Request::from_static("WATCH"),
+        Request::BulkString(key.into()),
+    ])
+}
+
+/// UNWATCH redis command
+///
+/// Flushes all the previously watched keys for a transaction.
+pub fn Unwatch() -> TransactionCommand {
+    TransactionCommand(Request::Array(vec![Request::from_static("UNWATCH")]))
+}
+
+pub struct TransactionCommand(Request);
+
+impl Command for TransactionCommand {
+    type Output = bool;
+
+    fn to_request(self) -> Request {
+        self.0
+    }
+
+    fn to_output(val: Response) -> Result<Self::Output, CommandError> {
+        match val {
+            Response::String(val) => Ok(val == "OK"),
+            _ => Err(CommandError::Output("Unexpected value", val)),
+        }
+    }
+}
+
+pub struct WatchCommand(Vec<Request>);
+
+impl WatchCommand {
+    /// Add a key to this command.
+    pub fn key<T>(mut self, other: T) -> Self
+    where
+        BulkString: From<T>,
+    {
+        self.0.push(other.into());
+        self
+    }
+
+    /// Add more keys to this command.
+    pub fn keys<T>(mut self, other: impl IntoIterator<Item = T>) -> Self
+    where
+        BulkString: From<T>,
+    {
+        self.0.extend(other.into_iter().map(|t| t.into()));
+        self
+    }
+}
+
+impl Command for WatchCommand {
+    type Output = bool;
+
+    fn to_request(self) -> Request {
+        Request::Array(self.0)
+    }
+
+    fn to_output(val: Response) -> Result<Self::Output, CommandError> {
+        TransactionCommand::to_output(val)
+    }
+}
+
+pub struct ExecCommand(Request);
+
+impl Command for ExecCommand {
+    type Output = Option<Vec<Response>>;
+
+    fn to_request(self) -> Request {
+        self.0
+    }
+
+    fn to_output(val: Response) -> Result<Self::Output, CommandError> {
+        match val {
+            Response::Nil => Ok(None),
+            Response::Array(items) => Ok(Some(items)),
+            _ => Err(CommandError::Output("Unexpected value", val)),
+        }
+    }
+}
@@ -1,5 +1,9 @@
 //! Redis protocol codec
-use std::{cmp, collections::HashMap, convert::TryFrom, hash::BuildHasher, hash::Hash, str};
+use std::collections::{HashMap, HashSet};
+use std::{
+    cell::Cell, cell::RefCell, cmp, convert::TryFrom, hash::BuildHasher, hash::Hash, hash::Hasher,
+    str,
+};
 
 use ntex::codec::{Decoder, Encoder};
 use ntex::util::{Buf, BufMut, ByteString, Bytes, BytesMut};
@@ -7,7 +11,93 @@ use ntex::util::{Buf, BufMut, ByteString, Bytes, BytesMut};
 use super::errors::Error;
 
 /// Codec to read/write redis values
-pub struct Codec;
+///
+/// Decoder is resumable, partially received aggregates are kept between
+/// `decode` calls, so data is parsed only once regardless of how it is
+/// split into reads. Use separate codec instance for each connection.
+///
+/// Decoder rejects replies that exceed configured limits with
+/// `Error::Limit` error.
+#[derive(Debug, Default)]
+pub struct Codec {
+    limits: Limits,
+    stack: RefCell<Vec<Aggregate>>,
+    consumed: Cell<usize>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Limits {
+    bulk_size: usize,
+    array_len: usize,
+    frame_size: usize,
+    depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            bulk_size: 512 * 1024 * 1024,
+            array_len: u32::MAX as usize,
+            frame_size: 1024 * 1024 * 1024,
+            depth: 32,
+        }
+    }
+}
+
+impl Codec {
+    /// Create new codec
+    pub fn new() -> Self {
+        Codec::default()
+    }
+
+    /// Set max size of bulk string, verbatim string and blob error
+    ///
+    /// By default max size is 512Mb.
+    pub fn max_bulk_size(mut self, size: usize) -> Self {
+        self.limits.bulk_size = size;
+        self
+    }
+
+    /// Set max number of elements of array, set, push, map or attribute
+    ///
+    /// Map and attribute size is number of entries. By default
+    /// max length is `u32::MAX`.
+    pub fn max_array_len(mut self, len: usize) -> Self {
+        self.limits.array_len = len;
+        self
+    }
+
+    /// Set max size of encoded reply, including all nested values
+    ///
+    /// By default max size is 1Gb.
+    pub fn max_frame_size(mut self, size: usize) -> Self {
+        self.limits.frame_size = size;
+        self
+    }
+
+    /// Set max nesting depth of aggregate replies
+    ///
+    /// By default max depth is 32.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.limits.depth = depth;
+        self
+    }
+
+    /// Aggregate reply is partially decoded
+    pub(crate) fn is_partial(&self) -> bool {
+        !self.stack.borrow().is_empty()
+    }
+}
+
+impl Clone for Codec {
+    /// Create codec with same limits, decoder state is not cloned
+    fn clone(&self) -> Self {
+        Codec {
+            limits: self.limits,
+            ..Default::default()
+        }
+    }
+}
 
 impl Encoder for Codec {
     type Item = Request;
@@ -57,12 +147,158 @@ impl Decoder for Codec {
     type Error = Error;
 
     fn decode(&self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        match decode(buf, 0)? {
-            Some((pos, item)) => {
-                buf.advance(pos);
-                Ok(Some(item))
+        let mut stack = self.stack.borrow_mut();
+        let len = buf.len();
+        let result = decode_frame(&self.limits, &mut stack, buf);
+
+        // incomplete reply is not consumed yet, it belongs to current frame
+        let consumed = self.consumed.get() + len - buf.len();
+        let size = if let Ok(None) = result {
+            consumed + buf.len()
+        } else {
+            consumed
+        };
+        let result = if size > self.limits.frame_size {
+            Err(Error::Limit(format!(
+                "Reply size exceeds {} bytes",
+                self.limits.frame_size
+            )))
+        } else {
+            result
+        };
+
+        match result {
+            Ok(None) => self.consumed.set(consumed),
+            _ => {
+                self.consumed.set(0);
+                stack.clear();
             }
-            None => Ok(None),
+        }
+        result
+    }
+}
+
+/// Part of streamed reply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplyChunk {
+    /// Chunk of bulk string payload
+    Bytes(Bytes),
+    /// Array element, or whole reply if reply is not a bulk string or an array
+    Element(Response),
+}
+
+/// Decoded part of streamed reply
+#[derive(Debug)]
+pub(crate) enum StreamFrame {
+    Chunk(ReplyChunk),
+    Error(ByteString),
+    End,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum StreamState {
+    Header,
+    Bulk(usize),
+    Elements(usize),
+    Done,
+}
+
+/// Decoder that yields bulk string and array replies in parts
+///
+/// Bulk string payload is split into chunks as data arrives, array
+/// elements are decoded one by one.
+#[derive(Debug)]
+pub(crate) struct StreamDecoder {
+    state: Cell<StreamState>,
+    codec: Codec,
+}
+
+impl StreamDecoder {
+    /// Elements are decoded with provided codec, bulk string is
+    /// not buffered so bulk size limit is not applied
+    pub(crate) fn new(codec: Codec) -> Self {
+        StreamDecoder {
+            state: Cell::new(StreamState::Header),
+            codec,
+        }
+    }
+
+    /// Reply is fully decoded
+    pub(crate) fn is_done(&self) -> bool {
+        self.state.get() == StreamState::Done
+    }
+
+    /// Stop decoding, rest of the reply is discarded
+    pub(crate) fn stop(&self) {
+        self.state.set(StreamState::Done)
+    }
+}
+
+impl Decoder for StreamDecoder {
+    type Item = StreamFrame;
+    type Error = Error;
+
+    fn decode(&self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            return match self.state.get() {
+                StreamState::Header => match buf.first() {
+                    Some(b'$') | Some(b'*') | Some(b'~') => match decode_length(buf, 1)? {
+                        Some((pos, -1)) => {
+                            buf.advance(pos);
+                            self.state.set(StreamState::Done);
+                            Ok(Some(StreamFrame::Chunk(ReplyChunk::Element(Response::Nil))))
+                        }
+                        Some((pos, size)) if size >= 0 => {
+                            self.state.set(if buf[0] == b'$' {
+                                StreamState::Bulk(size as usize)
+                            } else {
+                                StreamState::Elements(size as usize)
+                            });
+                            buf.advance(pos);
+                            continue;
+                        }
+                        Some((_, size)) => Err(Error::Parse(format!("Invalid size: {}", size))),
+                        None => Ok(None),
+                    },
+                    Some(_) => Ok(self.codec.decode(buf)?.map(|item| {
+                        self.state.set(StreamState::Done);
+                        match item {
+                            Response::Error(err) => StreamFrame::Error(err),
+                            item => StreamFrame::Chunk(ReplyChunk::Element(item)),
+                        }
+                    })),
+                    None => Ok(None),
+                },
+                StreamState::Bulk(0) => {
+                    if buf.len() < 2 {
+                        Ok(None)
+                    } else if &buf[..2] == b"\r\n" {
+                        buf.advance(2);
+                        self.state.set(StreamState::Done);
+                        Ok(Some(StreamFrame::End))
+                    } else {
+                        Err(Error::Parse("Invalid bulk string terminator".to_string()))
+                    }
+                }
+                StreamState::Bulk(remaining) => {
+                    if buf.is_empty() {
+                        Ok(None)
+                    } else {
+                        let size = cmp::min(remaining, buf.len());
+                        self.state.set(StreamState::Bulk(remaining - size));
+                        let chunk = buf.split_to(size).freeze();
+                        Ok(Some(StreamFrame::Chunk(ReplyChunk::Bytes(chunk))))
+                    }
+                }
+                StreamState::Elements(0) | StreamState::Done => {
+                    self.state.set(StreamState::Done);
+                    Ok(Some(StreamFrame::End))
+                }
+                StreamState::Elements(remaining) => Ok(self.codec.decode(buf)?.map(|item| {
+                    self.state.set(StreamState::Elements(remaining - 1));
+                    StreamFrame::Chunk(ReplyChunk::Element(item))
+                })),
+            };
         }
     }
 }
@@ -229,6 +465,20 @@ impl Request {
             }
         }
     }
+
+    /// Bytes of a single request argument, `None` for arrays
+    pub(crate) fn to_bytes(&self) -> Option<Bytes> {
+        match self {
+            Request::BulkString(val) => Some(val.0.clone()),
+            Request::BulkStatic(val) => Some(Bytes::from_static(val)),
+            Request::String(val) => Some(val.clone().into_bytes()),
+            Request::BulkInteger(val) | Request::Integer(val) => {
+                let mut buffer = itoa::Buffer::new();
+                Some(Bytes::copy_from_slice(buffer.format(*val).as_bytes()))
+            }
+            Request::Array(_) => None,
+        }
+    }
 }
 
 impl<T> From<T> for Request
@@ -309,26 +559,73 @@ pub enum Response {
     /// Redis documentation defines an integer as being a signed 64-bit integer:
     /// https://redis.io/topics/protocol#resp-integers
     Integer(i64),
+
+    /// RESP3 map of key-value pairs, order is preserved
+    Map(Vec<(Response, Response)>),
+
+    /// RESP3 unordered collection of elements
+    Set(Vec<Response>),
+
+    /// RESP3 floating point number
+    Double(Double),
+
+    /// RESP3 boolean value
+    Boolean(bool),
+
+    /// RESP3 big number, kept in textual representation
+    BigNumber(ByteString),
+
+    /// RESP3 verbatim string, contains format (`txt`, `mkd`) and content
+    Verbatim(ByteString, Bytes),
+
+    /// RESP3 attributes and the reply they describe
+    Attribute(Vec<(Response, Response)>, Box<Response>),
+
+    /// RESP3 out-of-band push data, like pubsub messages
+    Push(Vec<Response>),
 }
 
 impl Response {
     /// Extract redis server error to Result
+    ///
+    /// Attributes are stripped from the reply.
     pub fn into_result(self) -> Result<Response, ByteString> {
         match self {
             Response::Error(val) => Err(val),
+            Response::Attribute(_, val) => val.into_result(),
             val => Ok(val),
         }
     }
 }
 
+/// RESP3 double
+///
+/// Values are compared by bit pattern, so response could be used as
+/// a hash map key.
+#[derive(Debug, Copy, Clone)]
+pub struct Double(pub f64);
+
+impl PartialEq for Double {
+    fn eq(&self, other: &Double) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Double {}
+
+impl Hash for Double {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.0.to_bits())
+    }
+}
+
 impl TryFrom<Response> for Bytes {
     type Error = (&'static str, Response);
 
     fn try_from(val: Response) -> Result<Self, Self::Error> {
-        if let Response::Bytes(bytes) = val {
-            Ok(bytes)
-        } else {
-            Err(("Not a bytes object", val))
+        match val {
+            Response::Bytes(bytes) | Response::Verbatim(_, bytes) => Ok(bytes),
+            _ => Err(("Not a bytes object", val)),
         }
     }
 }
@@ -339,7 +636,7 @@ impl TryFrom<Response> for ByteString {
     fn try_from(val: Response) -> Result<Self, Self::Error> {
         match val {
             Response::String(val) => Ok(val),
-            Response::Bytes(val) => {
+            Response::Bytes(val) | Response::Verbatim(_, val) => {
                 if let Ok(val) = ByteString::try_from(val) {
                     Ok(val)
                 } else {
@@ -367,6 +664,9 @@ impl TryFrom<Response> for bool {
     type Error = (&'static str, Response);
 
     fn try_from(val: Response) -> Result<bool, Self::Error> {
+        if let Response::Boolean(val) = val {
+            return Ok(val);
+        }
         i64::try_from(val).and_then(|x| match x {
             0 => Ok(false),
             1 => Ok(true),
@@ -378,6 +678,25 @@ impl TryFrom<Response> for bool {
     }
 }
 
+impl TryFrom<Response> for f64 {
+    type Error = (&'static str, Response);
+
+    fn try_from(val: Response) -> Result<Self, Self::Error> {
+        match val {
+            Response::Double(val) => Ok(val.0),
+            Response::Integer(val) => Ok(val as f64),
+            Response::Bytes(ref bytes) => str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or(("Cannot be converted into a f64", val)),
+            Response::String(ref s) => s
+                .parse()
+                .map_err(|_| ("Cannot be converted into a f64", val.clone())),
+            _ => Err(("Cannot be converted into a f64", val)),
+        }
+    }
+}
+
 impl<T> TryFrom<Response> for Vec<T>
 where
     T: TryFrom<Response, Error = (&'static str, Response)>,
@@ -385,14 +704,36 @@ where
     type Error = (&'static str, Response);
 
     fn try_from(val: Response) -> Result<Vec<T>, Self::Error> {
-        if let Response::Array(ary) = val {
-            let mut ar = Vec::with_capacity(ary.len());
-            for value in ary {
-                ar.push(T::try_from(value)?);
+        match val {
+            Response::Array(ary) | Response::Set(ary) | Response::Push(ary) => {
+                let mut ar = Vec::with_capacity(ary.len());
+                for value in ary {
+                    ar.push(T::try_from(value)?);
+                }
+                Ok(ar)
             }
-            Ok(ar)
-        } else {
-            Err(("Cannot be converted into a vector", val))
+            _ => Err(("Cannot be converted into a vector", val)),
+        }
+    }
+}
+
+impl<T, S> TryFrom<Response> for HashSet<T, S>
+where
+    T: TryFrom<Response, Error = (&'static str, Response)> + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    type Error = (&'static str, Response);
+
+    fn try_from(val: Response) -> Result<HashSet<T, S>, Self::Error> {
+        match val {
+            Response::Array(ary) | Response::Set(ary) => {
+                let mut set = HashSet::with_capacity_and_hasher(ary.len(), S::default());
+                for value in ary {
+                    set.insert(T::try_from(value)?);
+                }
+                Ok(set)
+            }
+            _ => Err(("Cannot be converted into a hashset", val)),
         }
     }
 }
@@ -489,6 +830,13 @@ where
 
                 Ok(map)
             }
+            Response::Map(items) => {
+                let mut map = HashMap::with_capacity_and_hasher(items.len(), S::default());
+                for (k, v) in items {
+                    map.insert(K::try_from(k)?, T::try_from(v)?);
+                }
+                Ok(map)
+            }
             _ => Err(("Cannot be converted into a hashmap", val)),
         }
     }
@@ -503,11 +851,11 @@ macro_rules! impl_tryfrom_integers {
 
                 fn try_from(val: Response) -> Result<Self, Self::Error> {
                     i64::try_from(val).and_then(|x| {
-                        // $int_ty::max_value() as i64 > 0 should be optimized out. It tests if
+                        // $int_ty::MAX as i64 > 0 should be optimized out. It tests if
                         // the target integer type needs an "upper bounds" check
-                        if x < ($int_ty::min_value() as i64)
-                            || ($int_ty::max_value() as i64 > 0
-                                && x > ($int_ty::max_value() as i64))
+                        if x < ($int_ty::MIN as i64)
+                            || ($int_ty::MAX as i64 > 0
+                                && x > ($int_ty::MAX as i64))
                         {
                             Err((
                                 concat!(
@@ -528,11 +876,11 @@ macro_rules! impl_tryfrom_integers {
 
 impl_tryfrom_integers!(isize, usize, i32, u32, u64);
 
-fn write_rn(buf: &mut BytesMut) {
+pub(crate) fn write_rn(buf: &mut BytesMut) {
     buf.extend_from_slice(b"\r\n");
 }
 
-fn write_header(symb: u8, len: i64, buf: &mut BytesMut, body_size: usize) {
+pub(crate) fn write_header(symb: u8, len: i64, buf: &mut BytesMut, body_size: usize) {
     let mut len_buf = itoa::Buffer::new();
     let rendered = len_buf.format(len);
     buf.reserve(3 + rendered.len() + body_size);
@@ -541,7 +889,7 @@ fn write_header(symb: u8, len: i64, buf: &mut BytesMut, body_size: usize) {
     write_rn(buf);
 }
 
-fn write_string(symb: u8, string: &str, buf: &mut BytesMut) {
+pub(crate) fn write_string(symb: u8, string: &str, buf: &mut BytesMut) {
     let bytes = string.as_bytes();
     buf.reserve(3 + bytes.len());
     buf.put_u8(symb);
@@ -555,10 +903,15 @@ fn decode(buf: &mut BytesMut, idx: usize) -> DecodeResult {
     if buf.len() > idx {
         match buf[idx] {
             b'$' => decode_bytes(buf, idx + 1),
-            b'*' => decode_array(buf, idx + 1),
             b':' => decode_integer(buf, idx + 1),
             b'+' => decode_string(buf, idx + 1),
             b'-' => decode_error(buf, idx + 1),
+            b'_' => decode_null(buf, idx + 1),
+            b'#' => decode_boolean(buf, idx + 1),
+            b',' => decode_double(buf, idx + 1),
+            b'(' => decode_big_number(buf, idx + 1),
+            b'!' => decode_blob_error(buf, idx + 1),
+            b'=' => decode_verbatim(buf, idx + 1),
             _ => Err(Error::Parse(format!("Unexpected byte: {}", buf[idx]))),
         }
     } else {
@@ -566,7 +919,7 @@ fn decode(buf: &mut BytesMut, idx: usize) -> DecodeResult {
     }
 }
 
-fn decode_length(buf: &mut BytesMut, idx: usize) -> Result<Option<(usize, i64)>, Error> {
+fn decode_length(buf: &[u8], idx: usize) -> Result<Option<(usize, i64)>, Error> {
     // length is encoded as a string, terminated by "\r\n"
     let (pos, int_str) = if let Some(pos) = buf[idx..].windows(2).position(|w| w == b"\r\n") {
         (idx + pos + 2, &buf[idx..idx + pos])
@@ -603,37 +956,162 @@ fn decode_bytes(buf: &mut BytesMut, idx: usize) -> DecodeResult {
     }
 }
 
-fn decode_array(buf: &mut BytesMut, idx: usize) -> DecodeResult {
-    match decode_length(buf, idx)? {
-        Some((pos, -1)) => Ok(Some((pos, Response::Nil))),
-        Some((pos, size)) if size >= 0 => {
-            let size = size as usize;
+/// Upper bound for aggregate preallocation, announced size is not trusted
+const MAX_PREALLOC: usize = 1024;
 
-            // ensure all array items present in buffer
-            let items = buf[idx..].windows(2).filter(|w| w == b"\r\n").count() / 2;
-            if items < size {
-                return Ok(None);
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum AggregateKind {
+    Array,
+    Set,
+    Push,
+    Map,
+    Attribute,
+}
+
+/// Partially decoded aggregate
+#[derive(Debug)]
+struct Aggregate {
+    kind: AggregateKind,
+    remaining: usize,
+    items: Vec<Response>,
+}
+
+impl Aggregate {
+    fn new(kind: AggregateKind, size: usize) -> Result<Self, Error> {
+        let items = match kind {
+            AggregateKind::Map => size.checked_mul(2),
+            // attributes are followed by the reply they describe
+            AggregateKind::Attribute => size.checked_mul(2).and_then(|n| n.checked_add(1)),
+            _ => Some(size),
+        }
+        .ok_or_else(|| Error::Parse(format!("Invalid aggregate size: {}", size)))?;
+
+        Ok(Aggregate {
+            kind,
+            remaining: items,
+            items: Vec::with_capacity(cmp::min(items, MAX_PREALLOC)),
+        })
+    }
+
+    fn finish(self) -> Response {
+        match self.kind {
+            AggregateKind::Array => Response::Array(self.items),
+            AggregateKind::Set => Response::Set(self.items),
+            AggregateKind::Push => Response::Push(self.items),
+            AggregateKind::Map => Response::Map(into_pairs(self.items)),
+            AggregateKind::Attribute => {
+                let mut items = self.items;
+                let value = items.pop().unwrap_or(Response::Nil);
+                Response::Attribute(into_pairs(items), Box::new(value))
             }
+        }
+    }
+}
 
-            let mut pos = pos;
-            let mut values = Vec::with_capacity(size);
-            for _ in 0..size {
-                match decode(buf, pos) {
-                    Ok(None) => return Ok(None),
-                    Ok(Some((new_pos, value))) => {
-                        values.push(value);
-                        pos = new_pos;
-                    }
-                    Err(e) => return Err(e),
+enum Frame {
+    Value(Response),
+    Aggregate(Aggregate),
+}
+
+/// Decode next reply, complete values are consumed from the buffer
+/// and attached to the innermost unfinished aggregate
+fn decode_frame(
+    limits: &Limits,
+    stack: &mut Vec<Aggregate>,
+    buf: &mut BytesMut,
+) -> Result<Option<Response>, Error> {
+    'frame: loop {
+        let mut item = match decode_next(limits, buf)? {
+            Some(Frame::Value(item)) => item,
+            Some(Frame::Aggregate(aggregate)) if aggregate.remaining > 0 => {
+                if stack.len() >= limits.depth {
+                    return Err(Error::Limit(format!(
+                        "Reply nesting depth exceeds {}",
+                        limits.depth
+                    )));
                 }
+                stack.push(aggregate);
+                continue;
+            }
+            Some(Frame::Aggregate(aggregate)) => aggregate.finish(),
+            None => return Ok(None),
+        };
+
+        while let Some(mut parent) = stack.pop() {
+            parent.items.push(item);
+            parent.remaining -= 1;
+            if parent.remaining > 0 {
+                stack.push(parent);
+                continue 'frame;
+            }
+            item = parent.finish();
+        }
+        return Ok(Some(item));
+    }
+}
+
+/// Decode scalar value or aggregate header
+fn decode_next(limits: &Limits, buf: &mut BytesMut) -> Result<Option<Frame>, Error> {
+    let (kind, name) = match buf.first() {
+        Some(b'*') => (AggregateKind::Array, "array"),
+        Some(b'~') => (AggregateKind::Set, "set"),
+        Some(b'>') => (AggregateKind::Push, "push"),
+        Some(b'%') => (AggregateKind::Map, "map"),
+        Some(b'|') => (AggregateKind::Attribute, "attribute"),
+        Some(b'$') | Some(b'!') | Some(b'=') => {
+            // check size before payload is buffered
+            match decode_length(buf, 1)? {
+                Some((_, size)) if size > 0 && size as u64 > limits.bulk_size as u64 => {
+                    return Err(Error::Limit(format!(
+                        "Bulk string size exceeds {}: {}",
+                        limits.bulk_size, size
+                    )));
+                }
+                Some(_) => return decode_value(buf),
+                None => return Ok(None),
+            }
+        }
+        Some(_) => return decode_value(buf),
+        None => return Ok(None),
+    };
+
+    match decode_length(buf, 1)? {
+        Some((pos, size)) if size >= -1 => {
+            if size > 0 && size as u64 > limits.array_len as u64 {
+                return Err(Error::Limit(format!(
+                    "Length of {} exceeds {}: {}",
+                    name, limits.array_len, size
+                )));
+            }
+            buf.advance(pos);
+            if size == -1 && kind != AggregateKind::Attribute {
+                Ok(Some(Frame::Value(Response::Nil)))
+            } else {
+                let size = cmp::max(size, 0) as usize;
+                Ok(Some(Frame::Aggregate(Aggregate::new(kind, size)?)))
             }
-            Ok(Some((pos, Response::Array(values))))
         }
-        Some((_, size)) => Err(Error::Parse(format!("Invalid array size: {}", size))),
+        Some((_, size)) => Err(Error::Parse(format!("Invalid {} size: {}", name, size))),
         None => Ok(None),
     }
 }
 
+fn decode_value(buf: &mut BytesMut) -> Result<Option<Frame>, Error> {
+    Ok(decode(buf, 0)?.map(|(pos, item)| {
+        buf.advance(pos);
+        Frame::Value(item)
+    }))
+}
+
+fn into_pairs(items: Vec<Response>) -> Vec<(Response, Response)> {
+    let mut pairs = Vec::with_capacity(items.len() / 2);
+    let mut items = items.into_iter();
+    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+        pairs.push((key, value));
+    }
+    pairs
+}
+
 fn decode_integer(buf: &mut BytesMut, idx: usize) -> DecodeResult {
     if let Some((pos, int)) = decode_length(buf, idx)? {
         Ok(Some((pos, Response::Integer(int))))
@@ -659,14 +1137,79 @@ fn decode_error(buf: &mut BytesMut, idx: usize) -> DecodeResult {
     }
 }
 
+fn decode_null(buf: &mut BytesMut, idx: usize) -> DecodeResult {
+    if buf.len() < idx + 2 {
+        Ok(None)
+    } else if &buf[idx..idx + 2] == b"\r\n" {
+        Ok(Some((idx + 2, Response::Nil)))
+    } else {
+        Err(Error::Parse("Invalid null".to_string()))
+    }
+}
+
+fn decode_boolean(buf: &mut BytesMut, idx: usize) -> DecodeResult {
+    match scan_string(buf, idx)? {
+        Some((pos, val)) if val == "t" => Ok(Some((pos, Response::Boolean(true)))),
+        Some((pos, val)) if val == "f" => Ok(Some((pos, Response::Boolean(false)))),
+        Some((_, val)) => Err(Error::Parse(format!("Invalid boolean: {:?}", val))),
+        None => Ok(None),
+    }
+}
+
+fn decode_double(buf: &mut BytesMut, idx: usize) -> DecodeResult {
+    match scan_string(buf, idx)? {
+        Some((pos, val)) => match val.parse() {
+            Ok(val) => Ok(Some((pos, Response::Double(Double(val))))),
+            Err(_) => Err(Error::Parse(format!("Invalid double: {:?}", val))),
+        },
+        None => Ok(None),
+    }
+}
+
+fn decode_big_number(buf: &mut BytesMut, idx: usize) -> DecodeResult {
+    if let Some((pos, val)) = scan_string(buf, idx)? {
+        Ok(Some((pos, Response::BigNumber(val))))
+    } else {
+        Ok(None)
+    }
+}
+
+fn decode_blob_error(buf: &mut BytesMut, idx: usize) -> DecodeResult {
+    match decode_bytes(buf, idx)? {
+        Some((pos, Response::Bytes(val))) => match ByteString::try_from(val) {
+            Ok(val) => Ok(Some((pos, Response::Error(val)))),
+            Err(_) => Err(Error::Parse("Not a valid error string".to_string())),
+        },
+        result => Ok(result),
+    }
+}
+
+/// Verbatim string is a bulk string with `xxx:` format prefix
+fn decode_verbatim(buf: &mut BytesMut, idx: usize) -> DecodeResult {
+    match decode_bytes(buf, idx)? {
+        Some((pos, Response::Bytes(mut val))) => {
+            if val.len() < 4 || val[3] != b':' {
+                return Err(Error::Parse("Invalid verbatim string".to_string()));
+            }
+            let format = val.split_to(4);
+            match ByteString::try_from(format.slice(0..3)) {
+                Ok(format) => Ok(Some((pos, Response::Verbatim(format, val)))),
+                Err(_) => Err(Error::Parse("Invalid verbatim string".to_string())),
+            }
+        }
+        result => Ok(result),
+    }
+}
+
 fn scan_string(buf: &mut BytesMut, idx: usize) -> Result<Option<(usize, ByteString)>, Error> {
     if let Some(pos) = buf[idx..].windows(2).position(|w| w == b"\r\n") {
         buf.advance(idx);
-        match ByteString::try_from(buf.split_to(pos)) {
+        let data = buf.split_to(pos).freeze();
+        match ByteString::try_from(&data) {
             Ok(s) => Ok(Some((2, s))),
             Err(_) => Err(Error::Parse(format!(
                 "Not a valid string: {:?}",
-                &buf[idx..idx + cmp::min(pos, 10)]
+                &data[..cmp::min(pos, 10)]
             ))),
         }
     } else {
@@ -686,10 +1229,112 @@ mod tests {
 
     fn obj_to_bytes(obj: Request) -> Bytes {
         let mut bytes = BytesMut::new();
-        Codec.encode(obj, &mut bytes).unwrap();
+        Codec::default().encode(obj, &mut bytes).unwrap();
         bytes.freeze()
     }
 
+    fn stream(decoder: &StreamDecoder, buf: &mut BytesMut) -> Vec<StreamFrame> {
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.decode(buf).unwrap() {
+            let end = matches!(frame, StreamFrame::End);
+            frames.push(frame);
+            if end {
+                break;
+            }
+        }
+        frames
+    }
+
+    fn chunks(frames: Vec<StreamFrame>) -> Vec<ReplyChunk> {
+        frames
+            .into_iter()
+            .filter_map(|frame| match frame {
+                StreamFrame::Chunk(chunk) => Some(chunk),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_bulk() {
+        let decoder = StreamDecoder::new(Codec::default());
+        let mut buf = BytesMut::from(&b"$10\r\n0123"[..]);
+        assert_eq!(
+            chunks(stream(&decoder, &mut buf)),
+            vec![ReplyChunk::Bytes(Bytes::from_static(b"0123"))]
+        );
+        assert!(buf.is_empty());
+
+        buf.extend_from_slice(b"456789\r");
+        assert_eq!(
+            chunks(stream(&decoder, &mut buf)),
+            vec![ReplyChunk::Bytes(Bytes::from_static(b"456789"))]
+        );
+        assert!(!decoder.is_done());
+
+        buf.extend_from_slice(b"\n+OK\r\n");
+        assert!(matches!(
+            &stream(&decoder, &mut buf)[..],
+            &[StreamFrame::End]
+        ));
+        assert!(decoder.is_done());
+        assert_eq!(&buf[..], b"+OK\r\n");
+
+        let decoder = StreamDecoder::new(Codec::default());
+        let mut buf = BytesMut::from(&b"$-1\r\n"[..]);
+        assert_eq!(
+            chunks(stream(&decoder, &mut buf)),
+            vec![ReplyChunk::Element(Response::Nil)]
+        );
+        assert!(decoder.is_done());
+
+        let decoder = StreamDecoder::new(Codec::default());
+        let mut buf = BytesMut::from(&b"$0\r\n\r\n"[..]);
+        assert!(chunks(stream(&decoder, &mut buf)).is_empty());
+        assert!(decoder.is_done());
+
+        let decoder = StreamDecoder::new(Codec::default());
+        let mut buf = BytesMut::from(&b"$2\r\nabcd"[..]);
+        assert!(decoder.decode(&mut buf).is_ok());
+        assert!(decoder.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_stream_array() {
+        let decoder = StreamDecoder::new(Codec::default());
+        let mut buf = BytesMut::from(&b"*3\r\n$1\r\na\r\n:1\r\n$3\r\nb"[..]);
+        assert_eq!(
+            chunks(stream(&decoder, &mut buf)),
+            vec![
+                ReplyChunk::Element(Response::Bytes(Bytes::from_static(b"a"))),
+                ReplyChunk::Element(Response::Integer(1)),
+            ]
+        );
+        buf.extend_from_slice(b"cd\r\n");
+        let frames = stream(&decoder, &mut buf);
+        assert!(matches!(frames.last(), Some(StreamFrame::End)));
+        assert_eq!(
+            chunks(frames),
+            vec![ReplyChunk::Element(Response::Bytes(Bytes::from_static(
+                b"bcd"
+            )))]
+        );
+        assert!(buf.is_empty());
+
+        let decoder = StreamDecoder::new(Codec::default());
+        let mut buf = BytesMut::from(&b"-ERR fail\r\n"[..]);
+        let frames = stream(&decoder, &mut buf);
+        assert!(matches!(&frames[0], StreamFrame::Error(err) if err == "ERR fail"));
+        assert!(decoder.is_done());
+
+        let decoder = StreamDecoder::new(Codec::default());
+        let mut buf = BytesMut::from(&b":10\r\n"[..]);
+        assert_eq!(
+            chunks(stream(&decoder, &mut buf)),
+            vec![ReplyChunk::Element(Response::Integer(10))]
+        );
+    }
+
     #[test]
     fn test_array_macro() {
         let resp_object = array!["SET", "x"];
@@ -716,7 +1361,7 @@ mod tests {
     fn test_bulk_string() {
         let req_object = Request::BulkString(Bytes::from_static(b"THISISATEST").into());
         let mut bytes = BytesMut::new();
-        let codec = Codec;
+        let codec = Codec::default();
         codec.encode(req_object.clone(), &mut bytes).unwrap();
         assert_eq!(b"$11\r\nTHISISATEST\r\n".to_vec(), bytes.to_vec());
 
@@ -729,7 +1374,7 @@ mod tests {
     fn test_array() {
         let req_object = Request::Array(vec![b"TEST1".as_ref().into(), b"TEST2".as_ref().into()]);
         let mut bytes = BytesMut::new();
-        let codec = Codec;
+        let codec = Codec::default();
         codec.encode(req_object.clone(), &mut bytes).unwrap();
         assert_eq!(
             b"*2\r\n$5\r\nTEST1\r\n$5\r\nTEST2\r\n".to_vec(),
@@ -746,7 +1391,7 @@ mod tests {
 
     #[test]
     fn test_decode_array() {
-        let codec = Codec;
+        let codec = Codec::default();
 
         let resp = Response::Array(vec![
             Response::Bytes(Bytes::from_static(b"TEST1")),
@@ -774,14 +1419,14 @@ mod tests {
         let mut bytes = BytesMut::new();
         bytes.extend_from_slice(&b"$-1\r\n"[..]);
 
-        let codec = Codec;
+        let codec = Codec::default();
         let deserialized = codec.decode(&mut bytes).unwrap().unwrap();
         assert_eq!(deserialized, Response::Nil);
     }
 
     #[test]
     fn test_integer_overflow() {
-        let resp_object = Response::Integer(i64::max_value());
+        let resp_object = Response::Integer(i64::MAX);
         let res = i32::try_from(resp_object);
         assert!(res.is_err());
     }
@@ -802,14 +1447,8 @@ mod tests {
     #[test]
     fn test_hashmap_conversion() {
         let mut expected = HashMap::default();
-        expected.insert(
-            ByteString::from("KEY1").into(),
-            ByteString::from("VALUE1").into(),
-        );
-        expected.insert(
-            ByteString::from("KEY2").into(),
-            ByteString::from("VALUE2").into(),
-        );
+        expected.insert(ByteString::from("KEY1"), ByteString::from("VALUE1"));
+        expected.insert(ByteString::from("KEY2"), ByteString::from("VALUE2"));
 
         let resp_object = Response::Array(vec![
             Response::String(ByteString::from_static("KEY1")),
@@ -839,4 +1478,259 @@ mod tests {
             _ => panic!("Should not be able to convert an odd number of elements to a hashmap"),
         }
     }
+
+    fn decode_all(data: &[u8]) -> Response {
+        let mut bytes = BytesMut::copy_from_slice(data);
+        let item = Codec::default().decode(&mut bytes).unwrap().unwrap();
+        assert!(bytes.is_empty());
+        item
+    }
+
+    #[test]
+    fn test_decode_integer_array() {
+        assert_eq!(
+            decode_all(b"*2\r\n:1\r\n:2\r\n"),
+            Response::Array(vec![Response::Integer(1), Response::Integer(2)])
+        );
+    }
+
+    #[test]
+    fn test_decode_resp3_simple() {
+        assert_eq!(decode_all(b"_\r\n"), Response::Nil);
+        assert_eq!(decode_all(b"#t\r\n"), Response::Boolean(true));
+        assert_eq!(decode_all(b"#f\r\n"), Response::Boolean(false));
+        assert_eq!(decode_all(b",1.5\r\n"), Response::Double(Double(1.5)));
+        assert_eq!(
+            decode_all(b",-inf\r\n"),
+            Response::Double(Double(f64::NEG_INFINITY))
+        );
+        assert_eq!(
+            decode_all(b"(3492890328409238509324850943850943825024385\r\n"),
+            Response::BigNumber(ByteString::from_static(
+                "3492890328409238509324850943850943825024385"
+            ))
+        );
+        assert_eq!(
+            decode_all(b"!21\r\nSYNTAX invalid syntax\r\n"),
+            Response::Error(ByteString::from_static("SYNTAX invalid syntax"))
+        );
+        assert_eq!(
+            decode_all(b"=15\r\ntxt:Some string\r\n"),
+            Response::Verbatim(
+                ByteString::from_static("txt"),
+                Bytes::from_static(b"Some string")
+            )
+        );
+
+        let mut bytes = BytesMut::copy_from_slice(b"#x\r\n");
+        assert!(Codec::default().decode(&mut bytes).is_err());
+        let mut bytes = BytesMut::copy_from_slice(b"=3\r\ntxt\r\n");
+        assert!(Codec::default().decode(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_decode_resp3_aggregates() {
+        let map = Response::Map(vec![
+            (
+                Response::String(ByteString::from_static("first")),
+                Response::Integer(1),
+            ),
+            (
+                Response::String(ByteString::from_static("second")),
+                Response::Integer(2),
+            ),
+        ]);
+        assert_eq!(decode_all(b"%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n"), map);
+        assert_eq!(
+            decode_all(b"~2\r\n+a\r\n+b\r\n"),
+            Response::Set(vec![
+                Response::String(ByteString::from_static("a")),
+                Response::String(ByteString::from_static("b")),
+            ])
+        );
+        assert_eq!(
+            decode_all(b">2\r\n+pubsub\r\n$3\r\nmsg\r\n"),
+            Response::Push(vec![
+                Response::String(ByteString::from_static("pubsub")),
+                Response::Bytes(Bytes::from_static(b"msg")),
+            ])
+        );
+
+        let attr = decode_all(b"|1\r\n+ttl\r\n:3600\r\n:5\r\n");
+        assert_eq!(
+            attr,
+            Response::Attribute(
+                vec![(
+                    Response::String(ByteString::from_static("ttl")),
+                    Response::Integer(3600)
+                )],
+                Box::new(Response::Integer(5))
+            )
+        );
+        assert_eq!(attr.into_result().unwrap(), Response::Integer(5));
+
+        // incomplete map data
+        let codec = Codec::default();
+        let mut bytes = BytesMut::copy_from_slice(b"%2\r\n+first\r\n:1\r\n+second\r\n");
+        assert!(codec.decode(&mut bytes).unwrap().is_none());
+        bytes.extend_from_slice(b":2\r\n");
+        assert_eq!(codec.decode(&mut bytes).unwrap().unwrap(), map);
+    }
+
+    #[test]
+    fn test_decode_resumable() {
+        let data =
+            b"*3\r\n*2\r\n$3\r\nfoo\r\n%1\r\n+a\r\n*0\r\n*-1\r\n|1\r\n+ttl\r\n:1\r\n~1\r\n:2\r\n";
+        let expected = decode_all(data);
+        assert_eq!(
+            expected,
+            Response::Array(vec![
+                Response::Array(vec![
+                    Response::Bytes(Bytes::from_static(b"foo")),
+                    Response::Map(vec![(
+                        Response::String(ByteString::from_static("a")),
+                        Response::Array(vec![])
+                    )]),
+                ]),
+                Response::Nil,
+                Response::Attribute(
+                    vec![(
+                        Response::String(ByteString::from_static("ttl")),
+                        Response::Integer(1)
+                    )],
+                    Box::new(Response::Set(vec![Response::Integer(2)]))
+                ),
+            ])
+        );
+
+        // feed data byte by byte, decoded values are consumed
+        let codec = Codec::default();
+        let mut bytes = BytesMut::new();
+        for (idx, b) in data.iter().enumerate() {
+            bytes.extend_from_slice(&[*b]);
+            let item = codec.decode(&mut bytes).unwrap();
+            if idx + 1 < data.len() {
+                assert!(item.is_none());
+                assert!(bytes.len() <= 8);
+            } else {
+                assert_eq!(item.unwrap(), expected);
+            }
+        }
+        assert!(bytes.is_empty());
+
+        // next reply is decoded from scratch
+        bytes.extend_from_slice(b":3\r\n");
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap().unwrap(),
+            Response::Integer(3)
+        );
+
+        // decoder state is reset on error
+        let mut bytes = BytesMut::copy_from_slice(b"*2\r\n:1\r\n#x\r\n");
+        assert!(codec.decode(&mut bytes).is_err());
+        let mut bytes = BytesMut::copy_from_slice(b"+OK\r\n");
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap().unwrap(),
+            Response::String(ByteString::from_static("OK"))
+        );
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        fn is_limit(res: Result<Option<Response>, Error>) -> bool {
+            matches!(res, Err(Error::Limit(_)))
+        }
+
+        // bulk size is checked before payload is received
+        let codec = Codec::new().max_bulk_size(4);
+        let mut bytes = BytesMut::copy_from_slice(b"$4\r\ntest\r\n");
+        assert!(codec.decode(&mut bytes).unwrap().is_some());
+        let mut bytes = BytesMut::copy_from_slice(b"$1000000\r\n");
+        assert!(is_limit(codec.decode(&mut bytes)));
+        let mut bytes = BytesMut::copy_from_slice(b"*1\r\n=9\r\ntxt:value\r\n");
+        assert!(is_limit(codec.decode(&mut bytes)));
+
+        // decoder state is reset
+        let mut bytes = BytesMut::copy_from_slice(b"$-1\r\n");
+        assert_eq!(codec.decode(&mut bytes).unwrap().unwrap(), Response::Nil);
+
+        let codec = Codec::new().max_array_len(2);
+        let mut bytes = BytesMut::copy_from_slice(b"*2\r\n:1\r\n:2\r\n");
+        assert!(codec.decode(&mut bytes).unwrap().is_some());
+        let mut bytes = BytesMut::copy_from_slice(b"*2\r\n*3\r\n");
+        assert!(is_limit(codec.decode(&mut bytes)));
+        let mut bytes = BytesMut::copy_from_slice(b"%3\r\n");
+        assert!(is_limit(codec.decode(&mut bytes)));
+
+        let codec = Codec::new().max_depth(2);
+        let mut bytes = BytesMut::copy_from_slice(b"*1\r\n*1\r\n*0\r\n");
+        assert!(codec.decode(&mut bytes).unwrap().is_some());
+        let mut bytes = BytesMut::copy_from_slice(b"*1\r\n*1\r\n*1\r\n");
+        assert!(is_limit(codec.decode(&mut bytes)));
+        let mut bytes = BytesMut::from(&b"*1\r\n".repeat(100_000)[..]);
+        assert!(is_limit(codec.decode(&mut bytes)));
+
+        // frame size includes incomplete data
+        let codec = Codec::new().max_frame_size(20);
+        let mut bytes = BytesMut::copy_from_slice(b"*2\r\n+first\r\n");
+        assert!(codec.decode(&mut bytes).unwrap().is_none());
+        bytes.extend_from_slice(b"+seco");
+        assert!(codec.decode(&mut bytes).unwrap().is_none());
+        bytes.extend_from_slice(b"nd\r\n");
+        assert!(is_limit(codec.decode(&mut bytes)));
+        let mut bytes = BytesMut::copy_from_slice(b"+");
+        bytes.extend_from_slice(&[b'a'; 32]);
+        assert!(is_limit(codec.decode(&mut bytes)));
+        let mut bytes = BytesMut::copy_from_slice(b"*2\r\n:1\r\n:2\r\n");
+        assert!(codec.decode(&mut bytes).unwrap().is_some());
+
+        // cloned codec keeps limits
+        let mut bytes = BytesMut::copy_from_slice(b"*2\r\n+first\r\n+second\r\n");
+        assert!(is_limit(codec.clone().decode(&mut bytes)));
+    }
+
+    #[test]
+    fn test_decode_invalid_utf8() {
+        fn is_parse(data: &[u8]) -> bool {
+            let mut bytes = BytesMut::copy_from_slice(data);
+            matches!(Codec::default().decode(&mut bytes), Err(Error::Parse(_)))
+        }
+
+        assert!(is_parse(b"+\xff\xff\r\n"));
+        assert!(is_parse(b"-\xff\xff\xff\xff\r\n"));
+        assert!(is_parse(
+            b"*2\r\n+ok\r\n+\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\r\n"
+        ));
+        assert!(is_parse(b"*1\r\n-ERR \xff\r\n"));
+    }
+
+    #[test]
+    fn test_resp3_conversion() {
+        let resp_object = Response::Map(vec![(
+            Response::Bytes(Bytes::from_static(b"KEY1")),
+            Response::Bytes(Bytes::from_static(b"VALUE1")),
+        )]);
+        let map = HashMap::<Bytes, Bytes>::try_from(resp_object).unwrap();
+        assert_eq!(map[&Bytes::from_static(b"KEY1")], "VALUE1");
+
+        let resp_object = Response::Set(vec![Response::Integer(1), Response::Integer(2)]);
+        let set = HashSet::<i64>::try_from(resp_object.clone()).unwrap();
+        assert!(set.contains(&1) && set.contains(&2));
+        assert_eq!(Vec::<i64>::try_from(resp_object).unwrap(), vec![1, 2]);
+
+        assert!(bool::try_from(Response::Boolean(true)).unwrap());
+        assert_eq!(f64::try_from(Response::Double(Double(2.5))).unwrap(), 2.5);
+        assert_eq!(
+            f64::try_from(Response::Bytes(Bytes::from_static(b"2.5"))).unwrap(),
+            2.5
+        );
+        assert_eq!(
+            ByteString::try_from(Response::Verbatim(
+                ByteString::from_static("txt"),
+                Bytes::from_static(b"text")
+            ))
+            .unwrap(),
+            "text"
+        );
+    }
 }
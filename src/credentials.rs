@@ -0,0 +1,164 @@
+use std::{error::Error, future::Future, pin::Pin, rc::Rc};
+
+use ntex::time::{sleep, Millis};
+use ntex::util::ByteString;
+
+use super::{cmd, Client};
+
+/// Future returned by credentials provider
+pub type CredentialsFuture = Pin<Box<dyn Future<Output = Result<Credentials, Box<dyn Error>>>>>;
+
+/// Delay before retrying failed re-authentication
+const RETRY_DELAY: Millis = Millis(1_000);
+
+/// Minimal delay between re-authentications
+const MIN_DELAY: Millis = Millis(100);
+
+/// Connection credentials
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub(crate) username: Option<ByteString>,
+    pub(crate) password: ByteString,
+    pub(crate) expires_in: Option<Millis>,
+}
+
+impl Credentials {
+    /// Create credentials with password or token
+    pub fn new<P: AsRef<str>>(password: P) -> Self {
+        Credentials {
+            username: None,
+            password: ByteString::from(password.as_ref().to_string()),
+            expires_in: None,
+        }
+    }
+
+    /// Set ACL username
+    pub fn username<U: AsRef<str>>(mut self, username: U) -> Self {
+        self.username = Some(ByteString::from(username.as_ref().to_string()));
+        self
+    }
+
+    /// Set credentials lifetime
+    ///
+    /// Shared client re-authenticates with fresh credentials
+    /// before credentials expire.
+    pub fn expires_in<U: Into<Millis>>(mut self, lifetime: U) -> Self {
+        self.expires_in = Some(lifetime.into());
+        self
+    }
+}
+
+/// Credentials provider
+///
+/// Provider is consulted on every new connection and before
+/// credentials of the shared client expire.
+///
+/// ```rust,no_run
+/// use ntex_redis::{Credentials, CredentialsFuture, CredentialsProvider, RedisConnector};
+///
+/// struct TokenProvider;
+///
+/// impl CredentialsProvider for TokenProvider {
+///     fn credentials(&self) -> CredentialsFuture {
+///         Box::pin(async move {
+///             // fetch short-lived token
+///             Ok(Credentials::new("token").username("app").expires_in(ntex::time::Seconds(900)))
+///         })
+///     }
+/// }
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let redis = RedisConnector::new("127.0.0.1:6379")
+///         .credentials_provider(TokenProvider)
+///         .connect()
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+pub trait CredentialsProvider {
+    /// Fetch credentials
+    fn credentials(&self) -> CredentialsFuture;
+}
+
+impl<F, R> CredentialsProvider for F
+where
+    F: Fn() -> R,
+    R: Future<Output = Result<Credentials, Box<dyn Error>>> + 'static,
+{
+    fn credentials(&self) -> CredentialsFuture {
+        Box::pin((self)())
+    }
+}
+
+/// Re-authenticate shared client before credentials expire
+pub(crate) fn reauth(client: &Client, provider: Rc<dyn CredentialsProvider>, expires_in: Millis) {
+    let handles = client.handles();
+    let client = client.detached();
+    ntex::rt::spawn(async move {
+        let mut delay = refresh_delay(expires_in);
+        loop {
+            sleep(delay).await;
+            if handles.upgrade().is_none() || !client.is_connected() {
+                return;
+            }
+
+            let creds = match provider.credentials().await {
+                Ok(creds) => creds,
+                Err(e) => {
+                    log::warn!("Cannot fetch redis credentials: {}", e);
+                    delay = RETRY_DELAY;
+                    continue;
+                }
+            };
+            let mut auth = cmd::Auth(&creds.password);
+            if let Some(ref username) = creds.username {
+                auth = auth.username(username);
+            }
+            // AUTH is queued after in-flight commands
+            match client.exec(auth).await {
+                Ok(true) => {
+                    if let Some(expires_in) = creds.expires_in {
+                        delay = refresh_delay(expires_in);
+                    } else {
+                        return;
+                    }
+                }
+                Ok(false) => {
+                    log::warn!("Redis re-authentication failed");
+                    delay = RETRY_DELAY;
+                }
+                Err(e) => {
+                    log::warn!("Redis re-authentication failed: {}", e);
+                    delay = RETRY_DELAY;
+                }
+            }
+        }
+    });
+}
+
+/// Refresh credentials after 80% of lifetime
+fn refresh_delay(expires_in: Millis) -> Millis {
+    Millis(std::cmp::max(expires_in.0 - expires_in.0 / 5, MIN_DELAY.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_delay() {
+        assert_eq!(refresh_delay(Millis(1_000)), Millis(800));
+        assert_eq!(refresh_delay(Millis(900_000)), Millis(720_000));
+        assert_eq!(refresh_delay(Millis(0)), MIN_DELAY);
+    }
+
+    #[ntex::test]
+    async fn test_provider_fn() {
+        let provider = || async { Ok(Credentials::new("secret").username("user")) };
+        let creds = provider.credentials().await.unwrap();
+        assert_eq!(creds.password, "secret");
+        assert_eq!(creds.username.unwrap(), "user");
+        assert!(creds.expires_in.is_none());
+    }
+}
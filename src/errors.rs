@@ -13,6 +13,10 @@ pub enum Error {
     #[display(fmt = "Redis server response error: {}", _0)]
     Parse(String),
 
+    /// Reply exceeds decoder limits
+    #[display(fmt = "Redis server response exceeds limit: {}", _0)]
+    Limit(String),
+
     /// An IO error occurred
     #[display(fmt = "Io error: {:?}", _0)]
     PeerGone(Option<io::Error>),
@@ -24,6 +28,7 @@ impl Clone for Error {
     fn clone(&self) -> Self {
         match self {
             Error::Parse(_) => Error::Parse(String::new()),
+            Error::Limit(msg) => Error::Limit(msg.clone()),
             Error::PeerGone(_) => Error::PeerGone(None),
         }
     }
@@ -50,6 +55,26 @@ pub enum ConnectError {
     /// Auth command failed
     Unauthorized,
 
+    /// Credentials are rejected by server, contains server error
+    #[display(fmt = "Authentication failed: {}", _0)]
+    #[from(ignore)]
+    Auth(ByteString),
+
+    /// Credentials provider failed
+    #[display(fmt = "Cannot fetch credentials: {}", _0)]
+    #[from(ignore)]
+    Credentials(String),
+
+    /// Select command failed
+    #[display(fmt = "Cannot select database: {}", _0)]
+    #[from(ignore)]
+    SelectDb(u32),
+
+    /// Master cannot be resolved by any of the sentinels
+    #[display(fmt = "Cannot resolve master: {}", _0)]
+    #[from(ignore)]
+    NoMaster(String),
+
     /// Command execution error
     Command(CommandError),
 
@@ -59,6 +84,44 @@ pub enum ConnectError {
 
 impl std::error::Error for ConnectError {}
 
+#[derive(Debug, Display, Clone, PartialEq, Eq)]
+/// Redis connection url parse errors
+pub enum UrlError {
+    /// Url scheme is missing or not supported
+    #[display(fmt = "Unsupported url scheme: {:?}", _0)]
+    Scheme(String),
+
+    /// Url does not contain host or socket path
+    #[display(fmt = "Url host is missing")]
+    MissingHost,
+
+    /// Port is not a valid number
+    #[display(fmt = "Invalid port: {}", _0)]
+    Port(String),
+
+    /// Database index is not a valid number
+    #[display(fmt = "Invalid database index: {}", _0)]
+    Database(String),
+
+    /// Protocol version is not 2 or 3
+    #[display(fmt = "Invalid protocol version: {}", _0)]
+    Protocol(String),
+
+    /// Url component contains invalid percent encoding
+    #[display(fmt = "Invalid percent encoding")]
+    Encoding,
+
+    /// TLS connector cannot be configured
+    #[display(fmt = "Cannot configure TLS: {}", _0)]
+    Tls(String),
+
+    /// Transport is not available
+    #[display(fmt = "{} transport is not supported", _0)]
+    Transport(&'static str),
+}
+
+impl std::error::Error for UrlError {}
+
 #[derive(Debug, Display, From, Clone)]
 /// Redis command execution errors
 pub enum CommandError {
@@ -71,6 +134,18 @@ pub enum CommandError {
 
     /// Redis protocol level errors
     Protocol(Error),
+
+    /// Response is not received in time
+    #[display(fmt = "Command timed out")]
+    Timeout,
+
+    /// Command requires dedicated connection
+    #[display(fmt = "Command requires dedicated connection")]
+    SharedConnection,
+
+    /// Command requires connection with RESP3 protocol
+    #[display(fmt = "Command requires RESP3 protocol")]
+    Resp3Required,
 }
 
 impl std::error::Error for CommandError {}
@@ -31,16 +31,53 @@
 //! ```
 #![allow(clippy::return_self_not_must_use)]
 
+mod cache;
 mod client;
+mod cluster;
 pub mod cmd;
 pub mod codec;
 mod connector;
+mod credentials;
 pub mod errors;
+pub mod observer;
+mod pipeline;
+mod pool;
+mod pubsub;
+mod reconnect;
+mod sentinel;
+pub mod server;
 mod simple;
+mod subscription;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+mod tls;
+mod trace;
+mod transaction;
+#[cfg(unix)]
+mod unix;
+mod url;
 
+pub use self::cache::{CacheConfig, CacheStats, CachedClient, TrackingMode};
 pub use self::client::{Client, CommandResult};
-pub use self::connector::RedisConnector;
-pub use self::simple::{SimpleClient, SubscriptionClient};
+pub use self::cluster::ClusterClient;
+pub use self::connector::{BoxConnector, OnConnectFuture, RedisConnector};
+pub use self::credentials::{Credentials, CredentialsFuture, CredentialsProvider};
+pub use self::observer::{CommandEvent, Observer, Outcome};
+pub use self::pipeline::Pipeline;
+pub use self::pool::RedisPool;
+pub use self::pubsub::{PubSubManager, PubSubMessage, PubSubStream};
+pub use self::reconnect::{Backoff, ReconnectingClient, RetryPolicy};
+pub use self::sentinel::SentinelConnector;
+pub use self::simple::{ReplyStream, SimpleClient, SubscriptionClient};
+pub use self::subscription::Subscription;
+#[cfg(feature = "openssl")]
+pub use self::tls::OpensslConnector;
+#[cfg(feature = "rustls")]
+pub use self::tls::RustlsConnector;
+#[cfg(any(feature = "openssl", feature = "rustls"))]
+pub use self::tls::TlsConfig;
+pub use self::transaction::{Transaction, TransactionResult};
+#[cfg(unix)]
+pub use self::unix::UnixConnector;
 
 /// Macro to create a request array, useful for preparing commands to send. Elements can be any type, or a mixture
 /// of types, that satisfy `Into<Request>`.
@@ -0,0 +1,220 @@
+//! Client metrics
+use std::{convert::TryFrom, rc::Rc, time::Duration, time::Instant};
+
+use ntex::{time::now, util::ByteString};
+
+use crate::codec::{Request, Response};
+use crate::errors::{CommandError, Error};
+
+#[cfg(feature = "prometheus")]
+mod prometheus;
+
+#[cfg(feature = "prometheus")]
+pub use self::prometheus::PrometheusObserver;
+
+/// Command outcome
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// Redis replied with non-error response
+    Success,
+    /// Redis replied with error response
+    ServerError,
+    /// Response is not received within timeout
+    Timeout,
+    /// Connection is dropped before response is received
+    Disconnected,
+    /// Response cannot be decoded
+    Protocol,
+}
+
+impl Outcome {
+    /// Outcome name, suitable for metric labels
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Success => "success",
+            Outcome::ServerError => "server_error",
+            Outcome::Timeout => "timeout",
+            Outcome::Disconnected => "disconnected",
+            Outcome::Protocol => "protocol",
+        }
+    }
+
+    /// Outcome of command responses or error
+    pub(crate) fn from_result(result: Result<&[Response], &CommandError>) -> Self {
+        match result {
+            Ok(items) => {
+                if items.iter().any(|item| matches!(item, Response::Error(_))) {
+                    Outcome::ServerError
+                } else {
+                    Outcome::Success
+                }
+            }
+            Err(CommandError::Timeout) => Outcome::Timeout,
+            Err(CommandError::Protocol(e)) => Outcome::from_error(e),
+            Err(_) => Outcome::Protocol,
+        }
+    }
+
+    /// Outcome of protocol level error
+    pub(crate) fn from_error(err: &Error) -> Self {
+        match err {
+            Error::PeerGone(_) => Outcome::Disconnected,
+            _ => Outcome::Protocol,
+        }
+    }
+}
+
+/// Completed command metrics
+#[derive(Debug, Clone)]
+pub struct CommandEvent<'a> {
+    /// Command name, for pipelines and transactions name of the first command
+    pub name: &'a str,
+    /// Number of commands sent with one batch
+    pub commands: usize,
+    /// Time between sending command and receiving response
+    pub latency: Duration,
+    /// Command outcome
+    pub outcome: Outcome,
+    /// Size of encoded request
+    pub bytes_written: usize,
+    /// Size of received response
+    pub bytes_read: usize,
+    /// Number of commands waiting for response when command is sent
+    pub queue_len: usize,
+}
+
+/// Shared client activity observer
+///
+/// Observer is configured with `RedisConnector::observer()` and is
+/// notified about commands, pipelines and transactions executed with
+/// `Client`, including raw requests sent with `Service::call()`.
+///
+/// ```rust
+/// use ntex_redis::{cmd, CommandEvent, Observer, RedisConnector};
+///
+/// struct LogObserver;
+///
+/// impl Observer for LogObserver {
+///     fn command(&self, event: &CommandEvent<'_>) {
+///         println!("{} took {:?}: {}", event.name, event.latency, event.outcome.as_str());
+///     }
+/// }
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let redis = RedisConnector::new("127.0.0.1:6379")
+///         .observer(LogObserver)
+///         .connect()
+///         .await?;
+///     redis.exec(cmd::Ping()).await?;
+///     Ok(())
+/// }
+/// ```
+pub trait Observer {
+    /// Command is completed
+    fn command(&self, event: &CommandEvent<'_>);
+
+    /// Connection to redis is established
+    fn connected(&self, _address: &str) {}
+
+    /// Connection to redis is dropped
+    fn disconnected(&self, _address: &str) {}
+}
+
+/// In-flight command metrics
+pub(crate) struct Probe {
+    observer: Rc<dyn Observer>,
+    name: ByteString,
+    commands: usize,
+    queue_len: usize,
+    started: Instant,
+}
+
+impl Probe {
+    pub(crate) fn new(
+        observer: &Rc<dyn Observer>,
+        req: Option<&Request>,
+        commands: usize,
+        queue_len: usize,
+    ) -> Self {
+        let name = match req {
+            Some(Request::Array(args)) => args
+                .first()
+                .and_then(|name| name.to_bytes())
+                .and_then(|name| ByteString::try_from(name).ok())
+                .unwrap_or_default(),
+            _ => ByteString::default(),
+        };
+
+        Probe {
+            name,
+            commands,
+            queue_len,
+            observer: observer.clone(),
+            started: now(),
+        }
+    }
+
+    /// Report completed command
+    pub(crate) fn finish(self, outcome: Outcome, bytes_written: usize, bytes_read: usize) {
+        self.observer.command(&CommandEvent {
+            outcome,
+            bytes_written,
+            bytes_read,
+            name: &self.name,
+            commands: self.commands,
+            latency: now().saturating_duration_since(self.started),
+            queue_len: self.queue_len,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    type Event = (String, usize, Outcome, usize, usize);
+
+    #[derive(Default)]
+    struct Events(RefCell<Vec<Event>>);
+
+    impl Observer for Events {
+        fn command(&self, ev: &CommandEvent<'_>) {
+            self.0.borrow_mut().push((
+                ev.name.to_string(),
+                ev.commands,
+                ev.outcome,
+                ev.bytes_written,
+                ev.queue_len,
+            ));
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        let events = Rc::new(Events::default());
+        let observer: Rc<dyn Observer> = events.clone();
+        let req = crate::array!["GET", "key"];
+
+        let probe = Probe::new(&observer, Some(&req), 1, 2);
+        probe.finish(Outcome::from_result(Ok(&[Response::Nil])), 22, 5);
+        let probe = Probe::new(&observer, Some(&req), 2, 0);
+        let items = [Response::Nil, Response::Error("ERR".into())];
+        probe.finish(Outcome::from_result(Ok(&items)), 44, 10);
+        let probe = Probe::new(&observer, Some(&req), 1, 0);
+        probe.finish(Outcome::from_result(Err(&CommandError::Timeout)), 22, 0);
+        let probe = Probe::new(&observer, None, 1, 0);
+        probe.finish(Outcome::from_error(&Error::PeerGone(None)), 0, 0);
+
+        assert_eq!(
+            &*events.0.borrow(),
+            &[
+                ("GET".to_string(), 1, Outcome::Success, 22, 2),
+                ("GET".to_string(), 2, Outcome::ServerError, 44, 0),
+                ("GET".to_string(), 1, Outcome::Timeout, 22, 0),
+                (String::new(), 1, Outcome::Disconnected, 0, 0),
+            ]
+        );
+    }
+}
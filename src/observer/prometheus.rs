@@ -0,0 +1,138 @@
+use ::prometheus::{exponential_buckets, Histogram, HistogramOpts, HistogramVec};
+use ::prometheus::{IntCounter, IntGauge, Registry, Result};
+
+use super::{CommandEvent, Observer};
+
+/// Prometheus metrics observer
+///
+/// Registers following metrics:
+///
+/// * `redis_command_duration_seconds` histogram with `command` and `outcome` labels
+/// * `redis_pending_commands` histogram of response queue length
+/// * `redis_bytes_written_total` and `redis_bytes_read_total` counters
+/// * `redis_connections` gauge and `redis_disconnects_total` counter
+///
+/// ```rust
+/// use ntex_redis::{observer::PrometheusObserver, RedisConnector};
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let registry = prometheus::Registry::new();
+///     let redis = RedisConnector::new("127.0.0.1:6379")
+///         .observer(PrometheusObserver::new(&registry)?)
+///         .connect()
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct PrometheusObserver {
+    latency: HistogramVec,
+    pending: Histogram,
+    written: IntCounter,
+    read: IntCounter,
+    connections: IntGauge,
+    disconnects: IntCounter,
+}
+
+impl PrometheusObserver {
+    /// Create observer and register metrics
+    pub fn new(registry: &Registry) -> Result<Self> {
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "redis_command_duration_seconds",
+                "Redis command latency in seconds",
+            )
+            .buckets(exponential_buckets(0.0001, 2.0, 16)?),
+            &["command", "outcome"],
+        )?;
+        let pending = Histogram::with_opts(
+            HistogramOpts::new(
+                "redis_pending_commands",
+                "Number of commands waiting for response when command is sent",
+            )
+            .buckets(exponential_buckets(1.0, 2.0, 12)?),
+        )?;
+        let written = IntCounter::new("redis_bytes_written_total", "Bytes sent to redis")?;
+        let read = IntCounter::new("redis_bytes_read_total", "Bytes received from redis")?;
+        let connections = IntGauge::new("redis_connections", "Open redis connections")?;
+        let disconnects = IntCounter::new("redis_disconnects_total", "Dropped redis connections")?;
+
+        registry.register(Box::new(latency.clone()))?;
+        registry.register(Box::new(pending.clone()))?;
+        registry.register(Box::new(written.clone()))?;
+        registry.register(Box::new(read.clone()))?;
+        registry.register(Box::new(connections.clone()))?;
+        registry.register(Box::new(disconnects.clone()))?;
+
+        Ok(PrometheusObserver {
+            latency,
+            pending,
+            written,
+            read,
+            connections,
+            disconnects,
+        })
+    }
+}
+
+impl Observer for PrometheusObserver {
+    fn command(&self, event: &CommandEvent<'_>) {
+        self.latency
+            .with_label_values(&[event.name, event.outcome.as_str()])
+            .observe(event.latency.as_secs_f64());
+        self.pending.observe(event.queue_len as f64);
+        self.written.inc_by(event.bytes_written as u64);
+        self.read.inc_by(event.bytes_read as u64);
+    }
+
+    fn connected(&self, _: &str) {
+        self.connections.inc();
+    }
+
+    fn disconnected(&self, _: &str) {
+        self.connections.dec();
+        self.disconnects.inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::Outcome;
+
+    #[test]
+    fn test_metrics() {
+        let registry = Registry::new();
+        let observer = PrometheusObserver::new(&registry).unwrap();
+        assert!(PrometheusObserver::new(&registry).is_err());
+
+        observer.connected("127.0.0.1:6379");
+        observer.command(&CommandEvent {
+            name: "GET",
+            commands: 1,
+            latency: Duration::from_millis(1),
+            outcome: Outcome::Success,
+            bytes_written: 22,
+            bytes_read: 5,
+            queue_len: 0,
+        });
+        assert_eq!(observer.written.get(), 22);
+        assert_eq!(observer.read.get(), 5);
+        assert_eq!(observer.connections.get(), 1);
+        assert_eq!(
+            observer
+                .latency
+                .with_label_values(&["GET", "success"])
+                .get_sample_count(),
+            1
+        );
+
+        observer.disconnected("127.0.0.1:6379");
+        assert_eq!(observer.connections.get(), 0);
+        assert_eq!(observer.disconnects.get(), 1);
+        assert_eq!(registry.gather().len(), 6);
+    }
+}
@@ -0,0 +1,194 @@
+use super::cmd::Command;
+use super::codec::{Request, Response};
+use super::errors::CommandError;
+
+/// Set of commands executed as one batch
+pub trait Commands {
+    /// Batch output type
+    type Output;
+
+    /// Convert commands to redis requests
+    fn to_requests(self, reqs: &mut Vec<Request>);
+
+    /// Create batch output from redis responses
+    fn to_output(items: &mut dyn Iterator<Item = Response>) -> Self::Output;
+}
+
+/// Append command to a batch
+pub trait Append<C> {
+    /// Batch type with appended command
+    type Output;
+
+    fn append(self, cmd: C) -> Self::Output;
+}
+
+/// Pipeline of redis commands
+///
+/// Pipeline collects commands of different types and sends them in one
+/// write. Result is a tuple with output of each command.
+///
+/// ```rust
+/// use ntex_redis::{cmd, Pipeline, RedisConnector};
+/// # use rand::{thread_rng, Rng, distributions::Alphanumeric};
+/// # fn gen_random_key() -> String {
+/// #    thread_rng().sample_iter(&Alphanumeric).take(12).map(char::from).collect::<String>()
+/// # }
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let redis = RedisConnector::new("127.0.0.1:6379").connect().await?;
+///     let key = gen_random_key();
+///
+///     let pipeline = Pipeline::new()
+///         .add(cmd::Set(&key, "1"))
+///         .add(cmd::IncrBy(&key, 10))
+///         .add(cmd::Get(&key));
+///
+///     let (set, incr, get) = redis.pipeline(pipeline).await?;
+///     assert!(set?);
+///     assert_eq!(incr?, 11);
+///     assert_eq!(get?.unwrap(), "11");
+///
+///     Ok(())
+/// }
+/// ```
+pub struct Pipeline<T> {
+    cmds: T,
+}
+
+impl Pipeline<()> {
+    /// Create new pipeline
+    pub fn new() -> Self {
+        Pipeline { cmds: () }
+    }
+}
+
+impl Default for Pipeline<()> {
+    fn default() -> Self {
+        Pipeline::new()
+    }
+}
+
+impl<T> Pipeline<T> {
+    #[allow(clippy::should_implement_trait)]
+    /// Add command to the pipeline
+    pub fn add<C>(self, cmd: C) -> Pipeline<T::Output>
+    where
+        C: Command,
+        T: Append<C>,
+    {
+        Pipeline {
+            cmds: self.cmds.append(cmd),
+        }
+    }
+
+    pub(crate) fn into_requests(self) -> Vec<Request>
+    where
+        T: Commands,
+    {
+        let mut reqs = Vec::new();
+        self.cmds.to_requests(&mut reqs);
+        reqs
+    }
+}
+
+impl<C: Command> Pipeline<Vec<C>> {
+    /// Push command of the same type to the pipeline
+    pub fn push(&mut self, cmd: C) {
+        self.cmds.push(cmd);
+    }
+}
+
+impl<C: Command> From<Vec<C>> for Pipeline<Vec<C>> {
+    fn from(cmds: Vec<C>) -> Self {
+        Pipeline { cmds }
+    }
+}
+
+impl<C: Command> std::iter::FromIterator<C> for Pipeline<Vec<C>> {
+    fn from_iter<I: IntoIterator<Item = C>>(iter: I) -> Self {
+        Pipeline {
+            cmds: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Convert single response of a batch to command output
+pub(crate) fn output<C: Command>(item: Option<Response>) -> Result<C::Output, CommandError> {
+    match item {
+        Some(item) => C::to_output(item.into_result().map_err(CommandError::Error)?),
+        None => Err(CommandError::Output("Missing response", Response::Nil)),
+    }
+}
+
+impl Commands for () {
+    type Output = ();
+
+    fn to_requests(self, _: &mut Vec<Request>) {}
+
+    fn to_output(_: &mut dyn Iterator<Item = Response>) -> Self::Output {}
+}
+
+impl<C: Command> Append<C> for () {
+    type Output = (C,);
+
+    fn append(self, cmd: C) -> (C,) {
+        (cmd,)
+    }
+}
+
+impl<C: Command> Commands for Vec<C> {
+    type Output = Vec<Result<C::Output, CommandError>>;
+
+    fn to_requests(self, reqs: &mut Vec<Request>) {
+        reqs.extend(self.into_iter().map(|cmd| cmd.to_request()));
+    }
+
+    fn to_output(items: &mut dyn Iterator<Item = Response>) -> Self::Output {
+        items.map(|item| output::<C>(Some(item))).collect()
+    }
+}
+
+macro_rules! impl_commands_tuple {
+    ($($name:ident),+; $next:ident) => {
+        impl_commands_tuple!($($name),+);
+
+        impl<$($name: Command,)+ $next: Command> Append<$next> for ($($name,)+) {
+            type Output = ($($name,)+ $next,);
+
+            #[allow(non_snake_case)]
+            fn append(self, cmd: $next) -> Self::Output {
+                let ($($name,)+) = self;
+                ($($name,)+ cmd,)
+            }
+        }
+    };
+    ($($name:ident),+) => {
+        impl<$($name: Command),+> Commands for ($($name,)+) {
+            type Output = ($(Result<$name::Output, CommandError>,)+);
+
+            #[allow(non_snake_case)]
+            fn to_requests(self, reqs: &mut Vec<Request>) {
+                let ($($name,)+) = self;
+                $(reqs.push($name.to_request());)+
+            }
+
+            fn to_output(items: &mut dyn Iterator<Item = Response>) -> Self::Output {
+                ($(output::<$name>(items.next()),)+)
+            }
+        }
+    };
+}
+
+impl_commands_tuple!(A; B);
+impl_commands_tuple!(A, B; C);
+impl_commands_tuple!(A, B, C; D);
+impl_commands_tuple!(A, B, C, D; E);
+impl_commands_tuple!(A, B, C, D, E; F);
+impl_commands_tuple!(A, B, C, D, E, F; G);
+impl_commands_tuple!(A, B, C, D, E, F, G; H);
+impl_commands_tuple!(A, B, C, D, E, F, G, H; I);
+impl_commands_tuple!(A, B, C, D, E, F, G, H, I; J);
+impl_commands_tuple!(A, B, C, D, E, F, G, H, I, J; K);
+impl_commands_tuple!(A, B, C, D, E, F, G, H, I, J, K; L);
+impl_commands_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
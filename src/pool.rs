@@ -0,0 +1,208 @@
+use std::{
+    cell::Cell, cell::RefCell, future::Future, pin::Pin, rc::Rc, task::Context, task::Poll,
+};
+
+use ntex::connect::{self, Address, Connect};
+use ntex::{io::IoBoxed, service::Service};
+
+use super::cmd::Command;
+use super::codec::{Request, Response};
+use super::errors::ConnectError;
+use super::{Client, RedisConnector};
+
+/// Redis connection pool
+///
+/// Pool keeps a set of shared [`Client`] connections and hands out
+/// the least loaded one. Connections that are closed by the peer are
+/// replaced on demand.
+///
+/// ```rust
+/// use ntex_redis::{cmd, RedisConnector, RedisPool};
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let pool = RedisPool::new(RedisConnector::new("127.0.0.1:6379"))
+///         .min_size(2)
+///         .max_size(8);
+///
+///     pool.exec(cmd::Set("test", "value")).await?;
+///     let value = pool.exec(cmd::Get("test")).await?;
+///
+///     assert_eq!(value.unwrap(), "value");
+///     Ok(())
+/// }
+/// ```
+pub struct RedisPool<A, T> {
+    connector: Rc<RedisConnector<A, T>>,
+    clients: Rc<RefCell<Vec<Client>>>,
+    connecting: Rc<Cell<usize>>,
+    min_size: usize,
+    max_size: usize,
+}
+
+impl<A, T> RedisPool<A, T>
+where
+    A: Address + Clone,
+    T: Service<Connect<A>, Error = connect::ConnectError> + 'static,
+    IoBoxed: From<T::Response>,
+{
+    /// Create new redis pool
+    ///
+    /// By default pool keeps at least one and at most ten connections.
+    pub fn new(connector: RedisConnector<A, T>) -> Self {
+        RedisPool {
+            connector: Rc::new(connector),
+            clients: Rc::new(RefCell::new(Vec::new())),
+            connecting: Rc::new(Cell::new(0)),
+            min_size: 1,
+            max_size: 10,
+        }
+    }
+
+    /// Set minimum number of connections.
+    ///
+    /// Pool re-opens connections in background if number of live
+    /// connections drops below this value.
+    pub fn min_size(mut self, size: usize) -> Self {
+        self.min_size = size;
+        if self.max_size < size {
+            self.max_size = size;
+        }
+        self
+    }
+
+    /// Set maximum number of connections.
+    ///
+    /// New connection is opened only if all existing connections are busy.
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = std::cmp::max(size, 1);
+        if self.min_size > self.max_size {
+            self.min_size = self.max_size;
+        }
+        self
+    }
+
+    /// Open minimum number of connections
+    pub async fn warm_up(&self) -> Result<(), ConnectError> {
+        while self.size() + self.connecting.get() < self.min_size {
+            self.open().await?;
+        }
+        Ok(())
+    }
+
+    /// Number of live connections
+    ///
+    /// Disconnected and unhealthy connections are evicted from the pool.
+    pub fn size(&self) -> usize {
+        let mut clients = self.clients.borrow_mut();
+        clients.retain(|c| c.is_healthy());
+        clients.len()
+    }
+
+    /// Get least loaded connection from the pool
+    ///
+    /// Returned client is still shared with the pool. If all connections
+    /// are busy and new connection cannot be opened, busy connection
+    /// is returned.
+    pub async fn get(&self) -> Result<Client, ConnectError> {
+        let size = self.size();
+        let client = self
+            .clients
+            .borrow()
+            .iter()
+            .min_by_key(|c| c.pending())
+            .cloned();
+
+        if size + self.connecting.get() < self.min_size {
+            self.replenish();
+        }
+
+        match client {
+            Some(client) if client.pending() == 0 => Ok(client),
+            Some(client) if size + self.connecting.get() >= self.max_size => Ok(client),
+            Some(client) => match self.open().await {
+                Ok(client) => Ok(client),
+                // busy connection is better than no connection
+                Err(e) if client.is_healthy() => {
+                    log::warn!("Cannot open redis connection: {}", e);
+                    Ok(client)
+                }
+                Err(e) => Err(e),
+            },
+            None => self.open().await,
+        }
+    }
+
+    /// Open dedicated connection
+    ///
+    /// Connection is not shared with the pool, it could be used for
+    /// connection-scoped commands like `WATCH`, see [`Client::watch`].
+    pub async fn dedicated(&self) -> Result<Client, ConnectError> {
+        Ok(self.connector.connect().await?.into_dedicated())
+    }
+
+    /// Execute redis command on least loaded connection
+    pub async fn exec<U>(&self, cmd: U) -> Result<U::Output, ConnectError>
+    where
+        U: Command,
+    {
+        let client = self.get().await?;
+        Ok(client.exec(cmd).await?)
+    }
+
+    async fn open(&self) -> Result<Client, ConnectError> {
+        self.connecting.set(self.connecting.get() + 1);
+        let result = self.connector.connect().await;
+        self.connecting.set(self.connecting.get() - 1);
+
+        let client = result?;
+        self.clients.borrow_mut().push(client.clone());
+        Ok(client)
+    }
+
+    fn replenish(&self) {
+        let pool = self.clone();
+        ntex::rt::spawn(async move {
+            if let Err(e) = pool.warm_up().await {
+                log::warn!("Cannot open redis connection: {}", e);
+            }
+        });
+    }
+}
+
+impl<A, T> Clone for RedisPool<A, T> {
+    fn clone(&self) -> Self {
+        RedisPool {
+            connector: self.connector.clone(),
+            clients: self.clients.clone(),
+            connecting: self.connecting.clone(),
+            min_size: self.min_size,
+            max_size: self.max_size,
+        }
+    }
+}
+
+impl<A, T> Service<Request> for RedisPool<A, T>
+where
+    A: Address + Clone,
+    T: Service<Connect<A>, Error = connect::ConnectError> + 'static,
+    IoBoxed: From<T::Response>,
+{
+    type Response = Response;
+    type Error = ConnectError;
+    type Future<'f>
+        = Pin<Box<dyn Future<Output = Result<Response, ConnectError>> + 'f>>
+    where
+        Self: 'f;
+
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, req: Request) -> Self::Future<'_> {
+        Box::pin(async move {
+            let client = self.get().await?;
+            Ok(client.send(req, client.timeout()).await?)
+        })
+    }
+}
@@ -0,0 +1,522 @@
+use std::task::{Context, Poll};
+use std::{cell::Cell, cell::RefCell, future::Future, pin::Pin, rc::Rc, rc::Weak};
+
+use ntex::channel::mpsc;
+use ntex::connect::{self, Address, Connect};
+use ntex::time::sleep;
+use ntex::util::{poll_fn, Bytes, HashMap, Stream};
+use ntex::{io::IoBoxed, service::Service};
+
+use super::codec::{BulkString, Request, Response};
+use super::errors::ConnectError;
+use super::subscription::Kind;
+use super::{Backoff, RedisConnector, SimpleClient};
+
+type Dial = Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<SimpleClient, ConnectError>>>>>;
+
+/// Subscription key, kind and channel name or pattern
+type Key = (Kind, Bytes);
+
+type Senders = HashMap<Key, Vec<(usize, mpsc::Sender<PubSubMessage>)>>;
+
+/// Message received by subscription
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PubSubMessage {
+    /// Channel message is published to
+    pub channel: Bytes,
+    /// Matched pattern for pattern subscriptions
+    pub pattern: Option<Bytes>,
+    /// Message payload
+    pub payload: Bytes,
+}
+
+/// Subscription manager
+///
+/// Manager owns dedicated subscriber connection and hands out independent
+/// streams per channel, pattern or shard channel. Channel is subscribed
+/// with first stream and unsubscribed when last stream for the channel
+/// is dropped. If connection gets dropped, manager re-connects and
+/// re-issues all active subscriptions.
+///
+/// ```rust
+/// use ntex_redis::{cmd, PubSubManager, RedisConnector};
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let manager = PubSubManager::new(RedisConnector::new("127.0.0.1:6379"));
+///
+///     let news = manager.subscribe("news");
+///     let all = manager.psubscribe("news.*");
+///
+///     let redis = RedisConnector::new("127.0.0.1:6379").connect().await?;
+///     # ntex::time::sleep(ntex::time::Millis(100)).await;
+///     redis.exec(cmd::Publish("news", "hello")).await?;
+///
+///     let msg = news.recv().await.unwrap();
+///     assert_eq!(msg.payload, "hello");
+///     # drop(all);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct PubSubManager {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    dial: Dial,
+    backoff: RefCell<Backoff>,
+    client: RefCell<Option<Rc<SimpleClient>>>,
+    running: Cell<bool>,
+    subscriptions: RefCell<Senders>,
+    next_id: Cell<usize>,
+}
+
+impl PubSubManager {
+    /// Create new subscription manager
+    ///
+    /// Connection is opened with first subscription.
+    pub fn new<A, T>(connector: RedisConnector<A, T>) -> Self
+    where
+        A: Address + Clone,
+        T: Service<Connect<A>, Error = connect::ConnectError> + 'static,
+        IoBoxed: From<T::Response>,
+    {
+        let connector = Rc::new(connector);
+        PubSubManager {
+            inner: Rc::new(Inner {
+                dial: Box::new(move || {
+                    let connector = connector.clone();
+                    Box::pin(async move { connector.connect_simple().await })
+                }),
+                backoff: RefCell::new(Backoff::default()),
+                client: RefCell::new(None),
+                running: Cell::new(false),
+                subscriptions: RefCell::new(HashMap::default()),
+                next_id: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Set reconnect backoff
+    pub fn backoff(self, backoff: Backoff) -> Self {
+        *self.inner.backoff.borrow_mut() = backoff;
+        self
+    }
+
+    /// Returns true if subscriber connection is open
+    pub fn is_connected(&self) -> bool {
+        self.inner.client.borrow().is_some()
+    }
+
+    /// Subscribe to channel
+    pub fn subscribe<U>(&self, channel: U) -> PubSubStream
+    where
+        BulkString: From<U>,
+    {
+        self.add(Kind::Channel, channel)
+    }
+
+    /// Subscribe to channels matching glob-style pattern
+    pub fn psubscribe<U>(&self, pattern: U) -> PubSubStream
+    where
+        BulkString: From<U>,
+    {
+        self.add(Kind::Pattern, pattern)
+    }
+
+    /// Subscribe to shard channel
+    pub fn ssubscribe<U>(&self, channel: U) -> PubSubStream
+    where
+        BulkString: From<U>,
+    {
+        self.add(Kind::Shard, channel)
+    }
+
+    fn add<U>(&self, kind: Kind, channel: U) -> PubSubStream
+    where
+        BulkString: From<U>,
+    {
+        let channel = Request::BulkString(channel.into())
+            .to_bytes()
+            .unwrap_or_default();
+        let key = (kind, channel);
+        let (tx, rx) = mpsc::channel();
+        let id = self.inner.next_id.get() + 1;
+        self.inner.next_id.set(id);
+
+        let first = {
+            let mut subscriptions = self.inner.subscriptions.borrow_mut();
+            let senders = subscriptions.entry(key.clone()).or_default();
+            senders.push((id, tx));
+            senders.len() == 1
+        };
+        if first {
+            self.inner.send(kind.subscribe(), &key.1);
+        }
+        if !self.inner.running.replace(true) {
+            run(Rc::downgrade(&self.inner));
+        }
+
+        PubSubStream {
+            id,
+            key,
+            rx,
+            inner: Rc::downgrade(&self.inner),
+        }
+    }
+}
+
+impl Inner {
+    /// Send subscribe or unsubscribe command, if connected
+    fn send(&self, cmd: &'static str, channel: &Bytes) {
+        if let Some(client) = self.client.borrow().as_ref() {
+            let req = Request::Array(vec![Request::from_static(cmd), channel.into()]);
+            if let Err(e) = client.encode(req) {
+                log::warn!("Cannot send {} command: {}", cmd, e);
+            }
+        }
+    }
+
+    fn remove(&self, id: usize, key: &Key) {
+        let unused = {
+            let mut subscriptions = self.subscriptions.borrow_mut();
+            if let Some(senders) = subscriptions.get_mut(key) {
+                senders.retain(|(sid, _)| *sid != id);
+                if senders.is_empty() {
+                    subscriptions.remove(key);
+                    true
+                } else {
+                    false
+                }
+            } else {
+                false
+            }
+        };
+        if unused {
+            self.send(key.0.unsubscribe(), &key.1);
+        }
+    }
+
+    /// Re-issue all active subscriptions
+    fn resubscribe(&self, client: &SimpleClient) {
+        let subscriptions = self.subscriptions.borrow();
+        for kind in &[Kind::Channel, Kind::Pattern, Kind::Shard] {
+            let channels: Vec<Request> = subscriptions
+                .keys()
+                .filter(|(k, _)| k == kind)
+                .map(|(_, channel)| channel.into())
+                .collect();
+            if !channels.is_empty() {
+                let req = Request::from_static(kind.subscribe()).extend(channels);
+                if let Err(e) = client.encode(req) {
+                    log::warn!("Cannot resubscribe: {}", e);
+                }
+            }
+        }
+    }
+
+    fn route(&self, item: Response) {
+        let items = match item {
+            Response::Array(items) | Response::Push(items) => items,
+            Response::Error(e) => {
+                log::warn!("Subscription error: {}", e);
+                return;
+            }
+            _ => return,
+        };
+        let mut items = items.into_iter().map(|item| match item {
+            Response::Bytes(val) | Response::Verbatim(_, val) => Some(val),
+            Response::String(val) => Some(val.into_bytes()),
+            _ => None,
+        });
+        let name = items.next().flatten().unwrap_or_default();
+
+        let (kind, pattern) = match name.as_ref() {
+            b"message" => (Kind::Channel, None),
+            b"smessage" => (Kind::Shard, None),
+            b"pmessage" => (Kind::Pattern, items.next().flatten()),
+            _ => return,
+        };
+        let (channel, payload) = match (items.next().flatten(), items.next().flatten()) {
+            (Some(channel), Some(payload)) => (channel, payload),
+            _ => return,
+        };
+        let msg = PubSubMessage {
+            channel,
+            pattern,
+            payload,
+        };
+
+        let mut subscriptions = self.subscriptions.borrow_mut();
+        let key = match msg.pattern {
+            Some(ref pattern) => (kind, pattern.clone()),
+            None => (kind, msg.channel.clone()),
+        };
+        if let Some(senders) = subscriptions.get_mut(&key) {
+            senders.retain(|(_, tx)| tx.send(msg.clone()).is_ok());
+        } else if kind == Kind::Pattern {
+            // pattern could be reported in different form, match locally
+            for ((k, pattern), senders) in subscriptions.iter_mut() {
+                if *k == Kind::Pattern && glob_match(pattern, &msg.channel) {
+                    senders.retain(|(_, tx)| tx.send(msg.clone()).is_ok());
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.borrow_mut().take() {
+            client.close();
+        }
+    }
+}
+
+/// Maintain subscriber connection
+fn run(inner: Weak<Inner>) {
+    ntex::rt::spawn(async move {
+        let mut attempt: u32 = 0;
+        loop {
+            let (fut, backoff) = if let Some(inner) = inner.upgrade() {
+                let backoff = inner.backoff.borrow().clone();
+                ((inner.dial)(), backoff)
+            } else {
+                return;
+            };
+
+            let client = match fut.await {
+                Ok(client) => Rc::new(client),
+                Err(e) => {
+                    log::warn!("Cannot connect to redis: {}", e);
+                    sleep(backoff.delay(attempt)).await;
+                    attempt = attempt.saturating_add(1);
+                    continue;
+                }
+            };
+            attempt = 0;
+
+            if let Some(inner) = inner.upgrade() {
+                inner.resubscribe(&client);
+                *inner.client.borrow_mut() = Some(client.clone());
+            } else {
+                client.close();
+                return;
+            }
+
+            loop {
+                let item = client.recv_response().await;
+                let inner = if let Some(inner) = inner.upgrade() {
+                    inner
+                } else {
+                    return;
+                };
+                match item {
+                    Ok(item) => inner.route(item),
+                    Err(e) => {
+                        log::info!("Subscriber connection is dropped, reconnecting: {}", e);
+                        inner.client.borrow_mut().take();
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Messages of one subscription
+///
+/// Channel gets unsubscribed when last stream for the channel is dropped.
+pub struct PubSubStream {
+    id: usize,
+    key: Key,
+    rx: mpsc::Receiver<PubSubMessage>,
+    inner: Weak<Inner>,
+}
+
+impl PubSubStream {
+    /// Channel name or pattern of the subscription
+    pub fn channel(&self) -> &Bytes {
+        &self.key.1
+    }
+
+    /// Receive next message
+    pub async fn recv(&self) -> Option<PubSubMessage> {
+        poll_fn(|cx| self.rx.poll_recv(cx)).await
+    }
+
+    /// Attempt to pull out the next message, registering the current
+    /// task for wakeup if the message is not yet available.
+    pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<PubSubMessage>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Stream for PubSubStream {
+    type Item = PubSubMessage;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Drop for PubSubStream {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.upgrade() {
+            inner.remove(self.id, &self.key);
+        }
+    }
+}
+
+/// Redis glob-style pattern matching, supports `*`, `?`, `[...]` and `\`
+///
+/// On mismatch matching resumes from the last `*` only, so matching
+/// time does not grow exponentially with number of stars.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // pattern position after last `*` and text position it is tried at
+    let mut star = None;
+
+    while t < text.len() {
+        if pattern.get(p) == Some(&b'*') {
+            p += 1;
+            star = Some((p, t));
+            continue;
+        }
+        if let Some(next) = glob_match_char(pattern, p, text[t]) {
+            p = next;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p;
+            t = star_t + 1;
+            star = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|ch| *ch == b'*')
+}
+
+/// Match single character at pattern position, returns next pattern position
+fn glob_match_char(pattern: &[u8], p: usize, ch: u8) -> Option<usize> {
+    match pattern.get(p)? {
+        b'?' => Some(p + 1),
+        b'[' => {
+            let mut idx = p + 1;
+            let negate = pattern.get(idx) == Some(&b'^');
+            if negate {
+                idx += 1;
+            }
+            let mut matched = false;
+            while idx < pattern.len() && pattern[idx] != b']' {
+                if pattern[idx] == b'\\' && idx + 1 < pattern.len() {
+                    matched |= pattern[idx + 1] == ch;
+                    idx += 2;
+                } else if idx + 2 < pattern.len() && pattern[idx + 1] == b'-' {
+                    let (start, end) = if pattern[idx] <= pattern[idx + 2] {
+                        (pattern[idx], pattern[idx + 2])
+                    } else {
+                        (pattern[idx + 2], pattern[idx])
+                    };
+                    matched |= start <= ch && ch <= end;
+                    idx += 3;
+                } else {
+                    matched |= pattern[idx] == ch;
+                    idx += 1;
+                }
+            }
+            if matched != negate {
+                Some(std::cmp::min(idx + 1, pattern.len()))
+            } else {
+                None
+            }
+        }
+        b'\\' if p + 1 < pattern.len() => {
+            if pattern[p + 1] == ch {
+                Some(p + 2)
+            } else {
+                None
+            }
+        }
+        c if *c == ch => Some(p + 1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"news.*", b"news.it"));
+        assert!(glob_match(b"news.*", b"news."));
+        assert!(!glob_match(b"news.*", b"news"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(!glob_match(b"h?llo", b"hllo"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[ae]llo", b"hillo"));
+        assert!(glob_match(b"h[^e]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-c]llo", b"hbllo"));
+        assert!(glob_match(b"h\\*llo", b"h*llo"));
+        assert!(!glob_match(b"h\\*llo", b"hello"));
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"h*o*d", b"hello world"));
+        assert!(glob_match(b"*[0-9]", b"key:42"));
+        assert!(!glob_match(b"*[0-9]", b"key:a"));
+
+        // many stars must not backtrack exponentially
+        let text = [b'a'; 100];
+        assert!(!glob_match(b"a*a*a*a*a*a*a*a*a*a*a*a*b", &text));
+        assert!(glob_match(b"a*a*a*a*a*a*a*a*a*a*a*a*a", &text));
+        assert!(glob_match(b"**********a", &text));
+    }
+
+    #[ntex::test]
+    async fn test_route() {
+        let manager = PubSubManager {
+            inner: Rc::new(Inner {
+                dial: Box::new(|| {
+                    Box::pin(async { Err(ConnectError::NoMaster("test".to_string())) })
+                }),
+                backoff: RefCell::new(Backoff::default()),
+                client: RefCell::new(None),
+                running: Cell::new(true),
+                subscriptions: RefCell::new(HashMap::default()),
+                next_id: Cell::new(0),
+            }),
+        };
+        let bytes = |s: &'static str| Response::Bytes(Bytes::from_static(s.as_bytes()));
+
+        let news = manager.subscribe("news");
+        let news2 = manager.subscribe("news");
+        let pattern = manager.psubscribe("news.*");
+
+        manager.inner.route(Response::Array(vec![
+            bytes("message"),
+            bytes("news"),
+            bytes("hello"),
+        ]));
+        manager.inner.route(Response::Push(vec![
+            bytes("pmessage"),
+            bytes("news.*"),
+            bytes("news.it"),
+            bytes("ciao"),
+        ]));
+
+        assert_eq!(news.recv().await.unwrap().payload, "hello");
+        assert_eq!(news2.recv().await.unwrap().payload, "hello");
+        let msg = pattern.recv().await.unwrap();
+        assert_eq!(msg.channel, "news.it");
+        assert_eq!(msg.pattern.unwrap(), "news.*");
+
+        drop(news);
+        assert_eq!(manager.inner.subscriptions.borrow().len(), 2);
+        drop(news2);
+        drop(pattern);
+        assert!(manager.inner.subscriptions.borrow().is_empty());
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::{cell::Cell, cell::RefCell, future::Future, pin::Pin, rc::Rc, rc::Weak};
+
+use ntex::channel::oneshot;
+use ntex::connect::{self, Address, Connect};
+use ntex::time::{sleep, Millis};
+use ntex::{io::IoBoxed, service::Service};
+
+use super::cmd::Command;
+use super::codec::{Request, Response};
+use super::errors::{CommandError, ConnectError, Error};
+use super::{Client, RedisConnector};
+
+type Dial = Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<Client, ConnectError>>>>>;
+
+/// Exponential backoff for reconnect attempts
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Millis,
+    max: Millis,
+    factor: u32,
+    jitter: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Millis(100),
+            max: Millis::from_secs(30),
+            factor: 2,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl Backoff {
+    /// Create new backoff with initial and maximum delay
+    ///
+    /// Initial delay is at least 1 millisecond, so unreachable server
+    /// does not cause tight reconnect loop. Maximum delay is at least
+    /// initial delay.
+    pub fn new<T: Into<Millis>, U: Into<Millis>>(initial: T, max: U) -> Self {
+        let initial = std::cmp::max(initial.into(), Millis(1));
+        Backoff {
+            initial,
+            max: std::cmp::max(max.into(), initial),
+            ..Default::default()
+        }
+    }
+
+    /// Set delay multiplier, default is 2
+    pub fn factor(mut self, factor: u32) -> Self {
+        self.factor = std::cmp::max(factor, 1);
+        self
+    }
+
+    /// Set jitter as a fraction of the delay, default is 0.5
+    ///
+    /// Delay is randomly reduced by up to the specified fraction.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay before specified reconnect attempt
+    pub(crate) fn delay(&self, attempt: u32) -> Millis {
+        let max = u64::from(self.max.0);
+        let delay = (0..attempt)
+            .try_fold(u64::from(self.initial.0), |delay, _| {
+                let delay = delay.saturating_mul(u64::from(self.factor));
+                if delay < max {
+                    Ok(delay)
+                } else {
+                    Err(max)
+                }
+            })
+            .unwrap_or_else(|max| max);
+        let delay = std::cmp::min(delay, max);
+
+        if self.jitter > 0.0 {
+            let rnd = (random() % 1000) as f64 / 1000.0;
+            Millis(std::cmp::max(
+                (delay as f64 * (1.0 - self.jitter * rnd)) as u32,
+                1,
+            ))
+        } else {
+            Millis(delay as u32)
+        }
+    }
+}
+
+/// What to do with commands that were in flight when connection dropped
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RetryPolicy {
+    /// Fail in-flight commands with `PeerGone` error
+    Fail,
+    /// Re-send in-flight commands on a new connection, up to specified
+    /// number of times.
+    ///
+    /// Commands might be executed more than once, use only for idempotent
+    /// workloads.
+    Retry(usize),
+}
+
+/// Redis client that re-opens connection after it gets dropped
+///
+/// Connection setup configured on [`RedisConnector`], like auth passwords
+/// and database selection, is replayed for every new connection.
+///
+/// ```rust
+/// use ntex::time::Millis;
+/// use ntex_redis::{cmd, Backoff, ReconnectingClient, RedisConnector, RetryPolicy};
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let redis = ReconnectingClient::new(RedisConnector::new("127.0.0.1:6379"))
+///         .backoff(Backoff::new(Millis(50), Millis(5_000)))
+///         .retry(RetryPolicy::Retry(1));
+///
+///     redis.exec(cmd::Set("test", "value")).await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ReconnectingClient {
+    inner: Rc<Inner>,
+    backoff: Backoff,
+    policy: RetryPolicy,
+}
+
+struct Inner {
+    dial: Dial,
+    client: RefCell<Option<Client>>,
+    connecting: Cell<bool>,
+    waiters: RefCell<Vec<oneshot::Sender<Result<Client, ConnectError>>>>,
+}
+
+impl ReconnectingClient {
+    /// Create new reconnecting client
+    ///
+    /// Connection is opened on first use.
+    pub fn new<A, T>(connector: RedisConnector<A, T>) -> Self
+    where
+        A: Address + Clone,
+        T: Service<Connect<A>, Error = connect::ConnectError> + 'static,
+        IoBoxed: From<T::Response>,
+    {
+        let connector = Rc::new(connector);
+        Self::with_dial(Box::new(move || {
+            let connector = connector.clone();
+            Box::pin(async move { connector.connect().await })
+        }))
+    }
+
+    pub(crate) fn with_dial(dial: Dial) -> Self {
+        ReconnectingClient {
+            inner: Rc::new(Inner {
+                dial,
+                client: RefCell::new(None),
+                connecting: Cell::new(false),
+                waiters: RefCell::new(Vec::new()),
+            }),
+            backoff: Backoff::default(),
+            policy: RetryPolicy::Fail,
+        }
+    }
+
+    /// Set reconnect backoff
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set retry policy for in-flight commands, default is `RetryPolicy::Fail`
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns true if client has live connection to redis
+    pub fn is_connected(&self) -> bool {
+        self.inner
+            .client
+            .borrow()
+            .as_ref()
+            .map(|c| c.is_connected())
+            .unwrap_or(false)
+    }
+
+    /// Get current connection, wait for reconnect if connection is dropped
+    ///
+    /// If reconnect attempt fails, error is returned and client keeps
+    /// reconnecting in background.
+    pub async fn client(&self) -> Result<Client, ConnectError> {
+        if let Some(client) = self.inner.client.borrow().as_ref() {
+            if client.is_connected() {
+                return Ok(client.clone());
+            }
+        }
+
+        let (tx, rx) = oneshot::channel();
+        self.inner.waiters.borrow_mut().push(tx);
+        self.reconnect();
+        match rx.await {
+            Ok(result) => result,
+            Err(_) => Err(CommandError::Protocol(Error::PeerGone(None)).into()),
+        }
+    }
+
+    /// Execute redis command
+    pub async fn exec<U>(&self, cmd: U) -> Result<U::Output, ConnectError>
+    where
+        U: Command,
+    {
+        let res = self.send(cmd.to_request()).await?;
+        Ok(U::to_output(
+            res.into_result().map_err(CommandError::Error)?,
+        )?)
+    }
+
+    async fn send(&self, req: Request) -> Result<Response, ConnectError> {
+        let mut retries = match self.policy {
+            RetryPolicy::Fail => 0,
+            RetryPolicy::Retry(n) => n,
+        };
+        let mut req = Some(req);
+
+        loop {
+            let client = self.client().await?;
+            let item = if retries > 0 {
+                req.clone().unwrap()
+            } else {
+                req.take().unwrap()
+            };
+
+            match client.send(item, client.timeout()).await {
+                Err(CommandError::Protocol(Error::PeerGone(_))) if retries > 0 => {
+                    log::debug!("Redis connection is dropped, retry command");
+                    retries -= 1;
+                }
+                res => return Ok(res?),
+            }
+        }
+    }
+
+    fn reconnect(&self) {
+        if self.inner.connecting.replace(true) {
+            return;
+        }
+
+        let inner = Rc::downgrade(&self.inner);
+        let backoff = self.backoff.clone();
+        ntex::rt::spawn(async move {
+            let mut attempt = 0;
+            loop {
+                let fut = if let Some(inner) = inner.upgrade() {
+                    (inner.dial)()
+                } else {
+                    return;
+                };
+                let result = fut.await;
+
+                let inner = if let Some(inner) = inner.upgrade() {
+                    inner
+                } else {
+                    return;
+                };
+                let ok = result.is_ok();
+                match result {
+                    Ok(ref client) => {
+                        *inner.client.borrow_mut() = Some(client.clone());
+                        inner.connecting.set(false);
+                        watch(Rc::downgrade(&inner), client, backoff.clone());
+                    }
+                    Err(ref e) => log::warn!("Cannot connect to redis: {}", e),
+                }
+                for tx in inner.waiters.borrow_mut().drain(..) {
+                    let _ = tx.send(result.clone());
+                }
+                if ok {
+                    return;
+                }
+
+                drop(inner);
+                sleep(backoff.delay(attempt)).await;
+                attempt = attempt.saturating_add(1);
+            }
+        });
+    }
+}
+
+/// Re-open connection as soon as current one gets dropped
+fn watch(inner: Weak<Inner>, client: &Client, backoff: Backoff) {
+    let on_disconnect = client.on_disconnect();
+    ntex::rt::spawn(async move {
+        on_disconnect.await;
+        if let Some(inner) = inner.upgrade() {
+            log::info!("Redis connection is dropped, reconnecting");
+            ReconnectingClient {
+                inner,
+                backoff,
+                policy: RetryPolicy::Fail,
+            }
+            .reconnect();
+        }
+    });
+}
+
+impl Service<Request> for ReconnectingClient {
+    type Response = Response;
+    type Error = ConnectError;
+    type Future<'f>
+        = Pin<Box<dyn Future<Output = Result<Response, ConnectError>> + 'f>>
+    where
+        Self: 'f;
+
+    fn call(&self, req: Request) -> Self::Future<'_> {
+        Box::pin(self.send(req))
+    }
+}
+
+fn random() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+    );
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::new(Millis(100), Millis(1_000)).jitter(0.0);
+        assert_eq!(backoff.delay(0), Millis(100));
+        assert_eq!(backoff.delay(1), Millis(200));
+        assert_eq!(backoff.delay(3), Millis(800));
+        assert_eq!(backoff.delay(4), Millis(1_000));
+        assert_eq!(backoff.delay(u32::MAX), Millis(1_000));
+
+        let backoff = backoff.factor(3).jitter(0.5);
+        for _ in 0..100 {
+            let delay = backoff.delay(1);
+            assert!(delay >= Millis(150) && delay <= Millis(300));
+        }
+
+        // zero delays are clamped
+        let backoff = Backoff::new(Millis(0), Millis(0)).jitter(0.0);
+        assert_eq!(backoff.delay(0), Millis(1));
+        assert_eq!(backoff.delay(5), Millis(1));
+        let backoff = Backoff::new(Millis(0), Millis(100)).jitter(0.0);
+        assert_eq!(backoff.delay(0), Millis(1));
+        assert_eq!(backoff.delay(3), Millis(8));
+        let backoff = backoff.jitter(1.0);
+        for _ in 0..100 {
+            assert!(backoff.delay(0) >= Millis(1));
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::{cell::Cell, cell::RefCell, rc::Rc};
+
+use ntex::connect::{self, Connect};
+use ntex::time::{sleep, Millis};
+use ntex::util::ByteString;
+use ntex::{io::IoBoxed, service::Service};
+
+use super::cmd::{self, SubscribeItem};
+use super::errors::{CommandError, ConnectError};
+use super::{Client, ReconnectingClient, RedisConnector, SimpleClient};
+
+/// Sentinel aware redis connector
+///
+/// Connector asks sentinels for current master address with
+/// `SENTINEL GET-MASTER-ADDR-BY-NAME` and verifies it with `ROLE` command.
+/// Master connection is opened with [`RedisConnector`] settings, like auth
+/// passwords and database selection.
+///
+/// Connector subscribes to `+switch-master` events and closes all opened
+/// clients after failover. Use [`SentinelConnector::reconnecting`] to get
+/// a client that re-connects to the new master automatically.
+///
+/// ```rust,no_run
+/// use ntex_redis::{cmd, RedisConnector, SentinelConnector};
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let redis = SentinelConnector::new(
+///         "mymaster",
+///         RedisConnector::new("127.0.0.1:26379".to_string()).password("secret"),
+///     )
+///     .sentinel("127.0.0.1:26380")
+///     .reconnecting();
+///
+///     redis.exec(cmd::Set("test", "value")).await?;
+///
+///     Ok(())
+/// }
+/// ```
+pub struct SentinelConnector<T> {
+    name: String,
+    sentinels: Vec<String>,
+    password: Option<ByteString>,
+    connector: Rc<RedisConnector<String, T>>,
+    clients: Rc<RefCell<Vec<Client>>>,
+    watching: Rc<Cell<bool>>,
+}
+
+impl<T> Clone for SentinelConnector<T> {
+    fn clone(&self) -> Self {
+        SentinelConnector {
+            name: self.name.clone(),
+            sentinels: self.sentinels.clone(),
+            password: self.password.clone(),
+            connector: self.connector.clone(),
+            clients: self.clients.clone(),
+            watching: self.watching.clone(),
+        }
+    }
+}
+
+impl<T> SentinelConnector<T>
+where
+    T: Service<Connect<String>, Error = connect::ConnectError> + 'static,
+    IoBoxed: From<T::Response>,
+{
+    /// Create new sentinel connector for master with specified name
+    ///
+    /// Connector address is used as first sentinel address.
+    pub fn new<U: Into<String>>(name: U, connector: RedisConnector<String, T>) -> Self {
+        SentinelConnector {
+            name: name.into(),
+            sentinels: vec![connector.address().clone()],
+            password: None,
+            connector: Rc::new(connector),
+            clients: Rc::new(RefCell::new(Vec::new())),
+            watching: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Add sentinel address
+    pub fn sentinel<U: Into<String>>(mut self, address: U) -> Self {
+        self.sentinels.push(address.into());
+        self
+    }
+
+    /// Set sentinel auth password
+    ///
+    /// Password configured on `RedisConnector` is used for master only.
+    pub fn sentinel_password<U>(mut self, password: U) -> Self
+    where
+        U: AsRef<str>,
+    {
+        self.password = Some(ByteString::from(password.as_ref().to_string()));
+        self
+    }
+
+    /// Resolve current master address
+    pub async fn master_address(&self) -> Result<String, ConnectError> {
+        let mut error = None;
+        for sentinel in &self.sentinels {
+            match self.query(sentinel).await {
+                Ok(Some(address)) => return Ok(address),
+                Ok(None) => log::warn!("Sentinel {} does not know {}", sentinel, self.name),
+                Err(e) => {
+                    log::warn!("Cannot query sentinel {}: {}", sentinel, e);
+                    error = Some(e);
+                }
+            }
+        }
+        Err(error.unwrap_or_else(|| ConnectError::NoMaster(self.name.clone())))
+    }
+
+    /// Connect to current master and create shared client
+    ///
+    /// Client gets closed after failover.
+    pub async fn connect(&self) -> Result<Client, ConnectError> {
+        let mut error = None;
+        for sentinel in &self.sentinels {
+            let address = match self.query(sentinel).await {
+                Ok(Some(address)) => address,
+                Ok(None) => {
+                    log::warn!("Sentinel {} does not know {}", sentinel, self.name);
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("Cannot query sentinel {}: {}", sentinel, e);
+                    error = Some(e);
+                    continue;
+                }
+            };
+
+            let client = match self.connector.connect_to(address.clone()).await {
+                Ok(client) => client,
+                Err(e) => {
+                    log::warn!("Cannot connect to master {}: {}", address, e);
+                    error = Some(e);
+                    continue;
+                }
+            };
+
+            match client.exec(cmd::Role()).await {
+                Ok(role) if role == "master" => {
+                    self.watch();
+                    let mut clients = self.clients.borrow_mut();
+                    clients.retain(|c| c.is_connected());
+                    clients.push(client.clone());
+                    return Ok(client);
+                }
+                Ok(role) => {
+                    log::warn!("Master {} has role {}", address, role);
+                    client.close();
+                }
+                Err(e) => {
+                    log::warn!("Cannot check role of {}: {}", address, e);
+                    client.close();
+                    error = Some(e.into());
+                }
+            }
+        }
+        Err(error.unwrap_or_else(|| ConnectError::NoMaster(self.name.clone())))
+    }
+
+    /// Create client that re-connects to the new master after failover
+    pub fn reconnecting(self) -> ReconnectingClient {
+        let connector = Rc::new(self);
+        ReconnectingClient::with_dial(Box::new(move || {
+            let connector = connector.clone();
+            Box::pin(async move { connector.connect().await })
+        }))
+    }
+
+    async fn query(&self, sentinel: &str) -> Result<Option<String>, ConnectError> {
+        let client = sentinel_client(&self.connector, sentinel, &self.password).await?;
+        Ok(client
+            .exec(cmd::SentinelGetMasterAddr(self.name.as_str()))
+            .await?
+            .map(|(host, port)| format!("{}:{}", host, port)))
+    }
+
+    /// Subscribe to `+switch-master` events, clients are closed on failover
+    fn watch(&self) {
+        if self.watching.replace(true) {
+            return;
+        }
+
+        let name = self.name.clone();
+        let sentinels = self.sentinels.clone();
+        let password = self.password.clone();
+        let connector = Rc::downgrade(&self.connector);
+        let clients = Rc::downgrade(&self.clients);
+
+        ntex::rt::spawn(async move {
+            loop {
+                for sentinel in &sentinels {
+                    let client = if let Some(connector) = connector.upgrade() {
+                        sentinel_client(&connector, sentinel, &password).await
+                    } else {
+                        return;
+                    };
+                    let subscription = match client.and_then(|client| {
+                        Ok(client.subscribe(cmd::Subscribe(vec!["+switch-master"]))?)
+                    }) {
+                        Ok(subscription) => subscription,
+                        Err(e) => {
+                            log::warn!("Cannot subscribe to sentinel {}: {}", sentinel, e);
+                            continue;
+                        }
+                    };
+
+                    while let Some(item) = subscription.recv().await {
+                        match item {
+                            Ok(SubscribeItem::Message { payload, .. }) => {
+                                let clients = if let Some(clients) = clients.upgrade() {
+                                    clients
+                                } else {
+                                    return;
+                                };
+                                if let Some(address) = switch_master(&payload, &name) {
+                                    log::info!("Master {} is switched to {}", name, address);
+                                    for client in clients.borrow_mut().drain(..) {
+                                        client.close();
+                                    }
+                                }
+                            }
+                            Ok(_) => (),
+                            Err(e) => {
+                                log::warn!("Sentinel {} subscription failed: {}", sentinel, e);
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                sleep(Millis::from_secs(1)).await;
+                if clients.strong_count() == 0 {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+async fn sentinel_client<T>(
+    connector: &RedisConnector<String, T>,
+    sentinel: &str,
+    password: &Option<ByteString>,
+) -> Result<SimpleClient, ConnectError>
+where
+    T: Service<Connect<String>, Error = connect::ConnectError>,
+    IoBoxed: From<T::Response>,
+{
+    let client = connector.connect_plain(sentinel.to_string()).await?;
+    if let Some(password) = password {
+        match client.exec(cmd::Auth(password)).await {
+            Ok(true) => (),
+            Ok(false) => return Err(ConnectError::Unauthorized),
+            Err(CommandError::Error(e)) => return Err(ConnectError::Auth(e)),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(client)
+}
+
+/// Parse `+switch-master` payload: `<name> <old ip> <old port> <new ip> <new port>`
+fn switch_master(payload: &[u8], name: &str) -> Option<String> {
+    let payload = std::str::from_utf8(payload).ok()?;
+    let parts: Vec<_> = payload.split(' ').collect();
+    match parts.as_slice() {
+        [master, _, _, host, port] if *master == name => Some(format!("{}:{}", host, port)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_master() {
+        assert_eq!(
+            switch_master(b"mymaster 10.0.0.1 6379 10.0.0.2 6380", "mymaster"),
+            Some("10.0.0.2:6380".to_string())
+        );
+        assert_eq!(
+            switch_master(b"other 10.0.0.1 6379 10.0.0.2 6380", "mymaster"),
+            None
+        );
+        assert_eq!(switch_master(b"mymaster 10.0.0.1", "mymaster"), None);
+    }
+}
@@ -0,0 +1,409 @@
+//! Server side of redis protocol
+use std::cell::Cell;
+
+use ntex::codec::{Decoder, Encoder};
+use ntex::util::{BufMut, Bytes, BytesMut};
+
+use crate::codec::{write_header, write_rn, write_string, Codec, Double, Response};
+use crate::errors::Error;
+
+/// Max size of inline command line
+const MAX_INLINE_SIZE: usize = 64 * 1024;
+
+/// Command received from client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCommand {
+    parts: Vec<Bytes>,
+}
+
+impl ClientCommand {
+    /// Command name, as sent by client
+    pub fn name(&self) -> &Bytes {
+        &self.parts[0]
+    }
+
+    /// Check command name, comparison is case insensitive
+    pub fn is(&self, name: &str) -> bool {
+        self.parts[0].eq_ignore_ascii_case(name.as_bytes())
+    }
+
+    /// Command arguments
+    pub fn args(&self) -> &[Bytes] {
+        &self.parts[1..]
+    }
+
+    /// Command name and arguments
+    pub fn into_parts(self) -> Vec<Bytes> {
+        self.parts
+    }
+}
+
+/// Codec to read client commands and write responses
+///
+/// Commands are accepted in RESP multi-bulk format and as inline
+/// commands, inline command arguments are separated by whitespace.
+/// Responses are encoded in RESP2 format, RESP3 specific types are
+/// converted the same way redis server does, until RESP3 is enabled.
+#[derive(Debug, Clone)]
+pub struct ServerCodec {
+    codec: Codec,
+    resp3: Cell<bool>,
+}
+
+impl Default for ServerCodec {
+    fn default() -> Self {
+        ServerCodec::new(Codec::default())
+    }
+}
+
+impl ServerCodec {
+    /// Create server codec, commands are decoded with limits of provided codec
+    pub fn new(codec: Codec) -> Self {
+        ServerCodec {
+            codec: codec.max_depth(1),
+            resp3: Cell::new(false),
+        }
+    }
+
+    /// Responses are encoded in RESP3 format
+    pub fn is_resp3(&self) -> bool {
+        self.resp3.get()
+    }
+
+    /// Switch response encoding format
+    pub fn set_resp3(&self, resp3: bool) {
+        self.resp3.set(resp3)
+    }
+}
+
+impl Decoder for ServerCodec {
+    type Item = ClientCommand;
+    type Error = Error;
+
+    fn decode(&self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            if buf[0] != b'*' && !self.codec.is_partial() {
+                match decode_inline(buf)? {
+                    Some(parts) if parts.is_empty() => continue,
+                    Some(parts) => return Ok(Some(ClientCommand { parts })),
+                    None => return Ok(None),
+                }
+            }
+
+            let items = match self.codec.decode(buf)? {
+                Some(Response::Array(items)) => items,
+                Some(_) => continue,
+                None => return Ok(None),
+            };
+            // empty multi-bulk is ignored
+            if !items.is_empty() {
+                let mut parts = Vec::with_capacity(items.len());
+                for item in items {
+                    if let Response::Bytes(part) = item {
+                        parts.push(part);
+                    } else {
+                        return Err(Error::Parse("Expected bulk string argument".into()));
+                    }
+                }
+                return Ok(Some(ClientCommand { parts }));
+            }
+        }
+    }
+}
+
+impl Encoder for ServerCodec {
+    type Item = Response;
+    type Error = Error;
+
+    fn encode(&self, msg: Response, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        encode(msg, self.resp3.get(), buf);
+        Ok(())
+    }
+}
+
+/// Read inline command line, empty line is returned as empty command
+fn decode_inline(buf: &mut BytesMut) -> Result<Option<Vec<Bytes>>, Error> {
+    match buf.iter().position(|b| *b == b'\n') {
+        Some(pos) if pos < MAX_INLINE_SIZE => {
+            let line = buf.split_to(pos + 1).freeze();
+            let mut parts = Vec::new();
+            let mut start = None;
+            for (idx, b) in line.iter().enumerate() {
+                match (b.is_ascii_whitespace(), start) {
+                    (false, None) => start = Some(idx),
+                    (true, Some(s)) => {
+                        parts.push(line.slice(s..idx));
+                        start = None;
+                    }
+                    _ => (),
+                }
+            }
+            Ok(Some(parts))
+        }
+        None if buf.len() < MAX_INLINE_SIZE => Ok(None),
+        _ => Err(Error::Limit(format!(
+            "Inline command size exceeds {}",
+            MAX_INLINE_SIZE
+        ))),
+    }
+}
+
+fn encode(msg: Response, resp3: bool, buf: &mut BytesMut) {
+    match msg {
+        Response::Nil if resp3 => buf.extend_from_slice(b"_\r\n"),
+        Response::Nil => buf.extend_from_slice(b"$-1\r\n"),
+        Response::Array(items) => encode_items(b'*', items, resp3, buf),
+        Response::Bytes(data) => write_bulk(b'$', &data, buf),
+        Response::String(s) => write_line(b'+', &s, buf),
+        Response::Error(s) => write_line(b'-', &s, buf),
+        Response::Integer(val) => write_header(b':', val, buf, 0),
+        Response::Map(pairs) => {
+            if resp3 {
+                write_header(b'%', pairs.len() as i64, buf, 0);
+            } else {
+                write_header(b'*', pairs.len() as i64 * 2, buf, 0);
+            }
+            encode_pairs(pairs, resp3, buf);
+        }
+        Response::Set(items) => encode_items(if resp3 { b'~' } else { b'*' }, items, resp3, buf),
+        Response::Double(Double(val)) => {
+            let val = if val.is_nan() {
+                "nan".to_string()
+            } else if val.is_infinite() {
+                if val > 0.0 { "inf" } else { "-inf" }.to_string()
+            } else {
+                val.to_string()
+            };
+            if resp3 {
+                write_string(b',', &val, buf);
+            } else {
+                write_bulk(b'$', val.as_bytes(), buf);
+            }
+        }
+        Response::Boolean(val) if resp3 => {
+            buf.extend_from_slice(if val { b"#t\r\n" } else { b"#f\r\n" })
+        }
+        Response::Boolean(val) => write_header(b':', val as i64, buf, 0),
+        Response::BigNumber(val) if resp3 => write_line(b'(', &val, buf),
+        Response::BigNumber(val) => write_bulk(b'$', val.as_bytes(), buf),
+        Response::Verbatim(format, data) if resp3 => {
+            let size = format.len() + 1 + data.len();
+            write_header(b'=', size as i64, buf, size + 2);
+            buf.extend_from_slice(format.as_bytes());
+            buf.put_u8(b':');
+            buf.extend_from_slice(&data);
+            write_rn(buf);
+        }
+        Response::Verbatim(_, data) => write_bulk(b'$', &data, buf),
+        Response::Attribute(attrs, value) => {
+            // attributes are not supported by RESP2
+            if resp3 {
+                write_header(b'|', attrs.len() as i64, buf, 0);
+                encode_pairs(attrs, resp3, buf);
+            }
+            encode(*value, resp3, buf);
+        }
+        Response::Push(items) => encode_items(if resp3 { b'>' } else { b'*' }, items, resp3, buf),
+    }
+}
+
+fn encode_items(symb: u8, items: Vec<Response>, resp3: bool, buf: &mut BytesMut) {
+    write_header(symb, items.len() as i64, buf, 0);
+    for item in items {
+        encode(item, resp3, buf);
+    }
+}
+
+fn encode_pairs(pairs: Vec<(Response, Response)>, resp3: bool, buf: &mut BytesMut) {
+    for (key, value) in pairs {
+        encode(key, resp3, buf);
+        encode(value, resp3, buf);
+    }
+}
+
+fn write_bulk(symb: u8, data: &[u8], buf: &mut BytesMut) {
+    write_header(symb, data.len() as i64, buf, data.len() + 2);
+    buf.extend_from_slice(data);
+    write_rn(buf);
+}
+
+/// Simple strings cannot contain line breaks, they are replaced with spaces
+fn write_line(symb: u8, line: &str, buf: &mut BytesMut) {
+    if line.contains(['\r', '\n']) {
+        write_string(symb, &line.replace(['\r', '\n'], " "), buf);
+    } else {
+        write_string(symb, line, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ntex::util::ByteString;
+
+    use super::*;
+
+    fn decode_all(codec: &ServerCodec, data: &[u8]) -> Vec<Vec<Bytes>> {
+        let mut buf = BytesMut::copy_from_slice(data);
+        let mut commands = Vec::new();
+        while let Some(cmd) = codec.decode(&mut buf).unwrap() {
+            commands.push(cmd.into_parts());
+        }
+        assert!(buf.is_empty());
+        commands
+    }
+
+    fn encode_all(resp3: bool, msg: Response) -> Bytes {
+        let codec = ServerCodec::default();
+        codec.set_resp3(resp3);
+        let mut buf = BytesMut::new();
+        codec.encode(msg, &mut buf).unwrap();
+        buf.freeze()
+    }
+
+    #[test]
+    fn test_decode_commands() {
+        let codec = ServerCodec::default();
+        assert_eq!(
+            decode_all(
+                &codec,
+                b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\nPING\r\n\r\n  set  k\tv \n*0\r\n*1\r\n$4\r\nPING\r\n"
+            ),
+            vec![
+                vec![Bytes::from_static(b"GET"), Bytes::from_static(b"key")],
+                vec![Bytes::from_static(b"PING")],
+                vec![
+                    Bytes::from_static(b"set"),
+                    Bytes::from_static(b"k"),
+                    Bytes::from_static(b"v")
+                ],
+                vec![Bytes::from_static(b"PING")],
+            ]
+        );
+
+        // partially received commands
+        let mut buf = BytesMut::copy_from_slice(b"*2\r\n$4\r\nECHO\r\n$5\r\nhel");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        buf.extend_from_slice(b"lo\r\nPI");
+        let cmd = codec.decode(&mut buf).unwrap().unwrap();
+        assert!(cmd.is("echo"));
+        assert_eq!(cmd.name(), &Bytes::from_static(b"ECHO"));
+        assert_eq!(cmd.args(), &[Bytes::from_static(b"hello")]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        buf.extend_from_slice(b"NG\r\n");
+        assert!(codec.decode(&mut buf).unwrap().unwrap().is("PING"));
+
+        // commands must be arrays of bulk strings
+        let mut buf = BytesMut::copy_from_slice(b"*1\r\n:1\r\n");
+        assert!(matches!(codec.decode(&mut buf), Err(Error::Parse(_))));
+        let mut buf = BytesMut::copy_from_slice(b"*1\r\n*1\r\n$1\r\na\r\n");
+        assert!(matches!(codec.decode(&mut buf), Err(Error::Limit(_))));
+
+        // invalid utf-8 in simple strings is a protocol error
+        let mut buf = BytesMut::copy_from_slice(b"*2\r\n$4\r\nECHO\r\n+\xff\xff\xff\xff\r\n");
+        assert!(matches!(codec.decode(&mut buf), Err(Error::Parse(_))));
+        let mut buf = BytesMut::copy_from_slice(b"*1\r\n-\xff\xff\r\n");
+        assert!(matches!(codec.decode(&mut buf), Err(Error::Parse(_))));
+        let mut buf = BytesMut::from(&[b'a'; MAX_INLINE_SIZE][..]);
+        assert!(matches!(codec.decode(&mut buf), Err(Error::Limit(_))));
+
+        // limits of provided codec
+        let codec = ServerCodec::new(Codec::new().max_bulk_size(4));
+        let mut buf = BytesMut::copy_from_slice(b"*1\r\n$5\r\n");
+        assert!(matches!(codec.decode(&mut buf), Err(Error::Limit(_))));
+    }
+
+    #[test]
+    fn test_encode_resp2() {
+        assert_eq!(encode_all(false, Response::Nil), "$-1\r\n");
+        assert_eq!(
+            encode_all(false, Response::String(ByteString::from_static("OK"))),
+            "+OK\r\n"
+        );
+        assert_eq!(
+            encode_all(
+                false,
+                Response::Error(ByteString::from_static("ERR a\r\nb"))
+            ),
+            "-ERR a  b\r\n"
+        );
+        assert_eq!(encode_all(false, Response::Integer(-5)), ":-5\r\n");
+        assert_eq!(encode_all(false, Response::Boolean(true)), ":1\r\n");
+        assert_eq!(
+            encode_all(false, Response::Double(Double(1.5))),
+            "$3\r\n1.5\r\n"
+        );
+        assert_eq!(
+            encode_all(false, Response::BigNumber(ByteString::from_static("123"))),
+            "$3\r\n123\r\n"
+        );
+        assert_eq!(
+            encode_all(
+                false,
+                Response::Verbatim(ByteString::from_static("txt"), Bytes::from_static(b"hi"))
+            ),
+            "$2\r\nhi\r\n"
+        );
+        assert_eq!(
+            encode_all(
+                false,
+                Response::Map(vec![(
+                    Response::Bytes(Bytes::from_static(b"k")),
+                    Response::Set(vec![Response::Integer(1)])
+                )])
+            ),
+            "*2\r\n$1\r\nk\r\n*1\r\n:1\r\n"
+        );
+        assert_eq!(
+            encode_all(
+                false,
+                Response::Attribute(
+                    vec![(Response::Integer(1), Response::Integer(2))],
+                    Box::new(Response::Push(vec![Response::Nil]))
+                )
+            ),
+            "*1\r\n$-1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_resp3() {
+        let values = vec![
+            Response::Nil,
+            Response::Boolean(false),
+            Response::Double(Double(f64::NEG_INFINITY)),
+            Response::Double(Double(2.25)),
+            Response::BigNumber(ByteString::from_static("1234567890123456789012")),
+            Response::Verbatim(ByteString::from_static("txt"), Bytes::from_static(b"hi")),
+            Response::Map(vec![(
+                Response::String(ByteString::from_static("k")),
+                Response::Set(vec![Response::Integer(1)]),
+            )]),
+            Response::Attribute(
+                vec![(Response::Integer(1), Response::Integer(2))],
+                Box::new(Response::Push(vec![Response::Bytes(Bytes::from_static(
+                    b"msg",
+                ))])),
+            ),
+        ];
+        assert_eq!(
+            encode_all(true, Response::Array(values.clone())),
+            "*8\r\n_\r\n#f\r\n,-inf\r\n,2.25\r\n(1234567890123456789012\r\n=6\r\ntxt:hi\r\n\
+             %1\r\n+k\r\n~1\r\n:1\r\n|1\r\n:1\r\n:2\r\n>1\r\n$3\r\nmsg\r\n"
+        );
+
+        // encoded values are decoded back
+        let mut buf = BytesMut::new();
+        let codec = ServerCodec::default();
+        codec.set_resp3(true);
+        codec
+            .encode(Response::Array(values.clone()), &mut buf)
+            .unwrap();
+        assert_eq!(
+            Codec::default().decode(&mut buf).unwrap().unwrap(),
+            Response::Array(values)
+        );
+    }
+}
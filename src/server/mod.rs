@@ -0,0 +1,189 @@
+//! Redis protocol server
+//!
+//! [`RedisServer`] accepts connections and dispatches client commands
+//! to user service, replies are sent back in order of commands.
+//!
+//! ```rust,no_run
+//! use ntex::service::fn_service;
+//! use ntex_redis::codec::Response;
+//! use ntex_redis::server::{ClientCommand, RedisServer};
+//!
+//! #[ntex::main]
+//! async fn main() -> std::io::Result<()> {
+//!     ntex::server::build()
+//!         .bind("redis", "127.0.0.1:6380", |_| {
+//!             RedisServer::new(fn_service(|cmd: ClientCommand| async move {
+//!                 Ok::<_, ()>(if cmd.is("PING") {
+//!                     Response::String("PONG".into())
+//!                 } else {
+//!                     Response::Error("ERR unknown command".into())
+//!                 })
+//!             }))
+//!         })?
+//!         .run()
+//!         .await
+//! }
+//! ```
+use std::{fmt, future::Future, pin::Pin, rc::Rc};
+
+use ntex::io::{Filter, Io};
+use ntex::service::{Service, ServiceFactory};
+use ntex::util::{poll_fn, Either, Ready};
+
+use crate::codec::{Codec, Response};
+use crate::errors::Error;
+
+mod codec;
+
+pub use self::codec::{ClientCommand, ServerCodec};
+
+/// Service factory for `ntex` server
+///
+/// Command service is created for each connection, so it can keep
+/// connection state. Service replies with `Response`, service error
+/// closes connection.
+///
+/// `HELLO` command with protocol version switches connection to RESP2
+/// or RESP3 format, unless service replies with error. Connection is
+/// closed after reply to `QUIT` command.
+pub struct RedisServer<F> {
+    factory: Rc<F>,
+    codec: Codec,
+}
+
+impl<F> RedisServer<F>
+where
+    F: ServiceFactory<ClientCommand, Response = Response>,
+{
+    /// Create redis server with command service factory
+    pub fn new(factory: F) -> Self {
+        RedisServer {
+            factory: Rc::new(factory),
+            codec: Codec::default(),
+        }
+    }
+
+    /// Set command decoder limits
+    ///
+    /// Inline commands are limited to 64Kb.
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+}
+
+impl<F, T> ServiceFactory<Io<T>> for RedisServer<F>
+where
+    F: ServiceFactory<ClientCommand, Response = Response> + 'static,
+    F::Error: fmt::Debug,
+    F::InitError: fmt::Debug,
+    T: Filter,
+{
+    type Response = ();
+    type Error = Error;
+    type Service = RedisService<F>;
+    type InitError = ();
+    type Future<'f> = Ready<Self::Service, Self::InitError>;
+
+    fn create(&self, _: ()) -> Self::Future<'_> {
+        Ready::Ok(RedisService {
+            factory: self.factory.clone(),
+            codec: self.codec.clone(),
+        })
+    }
+}
+
+/// Service that handles redis protocol connection
+pub struct RedisService<F> {
+    factory: Rc<F>,
+    codec: Codec,
+}
+
+impl<F, T> Service<Io<T>> for RedisService<F>
+where
+    F: ServiceFactory<ClientCommand, Response = Response> + 'static,
+    F::Error: fmt::Debug,
+    F::InitError: fmt::Debug,
+    T: Filter,
+{
+    type Response = ();
+    type Error = Error;
+    type Future<'f> = Pin<Box<dyn Future<Output = Result<(), Error>> + 'f>>;
+
+    fn call(&self, io: Io<T>) -> Self::Future<'_> {
+        let codec = ServerCodec::new(self.codec.clone());
+        Box::pin(dispatch(io, self.factory.clone(), codec))
+    }
+}
+
+async fn dispatch<F, T>(io: Io<T>, factory: Rc<F>, codec: ServerCodec) -> Result<(), Error>
+where
+    F: ServiceFactory<ClientCommand, Response = Response>,
+    F::Error: fmt::Debug,
+    F::InitError: fmt::Debug,
+{
+    let service = match factory.create(()).await {
+        Ok(service) => service,
+        Err(e) => {
+            log::error!("Cannot create redis command service: {:?}", e);
+            io.close();
+            return Ok(());
+        }
+    };
+
+    loop {
+        let cmd = match io.recv(&codec).await {
+            Ok(Some(cmd)) => cmd,
+            Ok(None) => return Ok(()),
+            Err(Either::Left(err)) => {
+                // report protocol error before closing connection
+                let msg = match err {
+                    Error::Parse(ref msg) | Error::Limit(ref msg) => msg.as_str(),
+                    Error::PeerGone(_) => "connection error",
+                };
+                let reply = Response::Error(format!("ERR Protocol error: {}", msg).into());
+                if io.encode(reply, &codec).is_ok() {
+                    let _ = io.shutdown().await;
+                }
+                return Err(err);
+            }
+            Err(Either::Right(err)) => return Err(Error::PeerGone(Some(err))),
+        };
+
+        if let Err(e) = poll_fn(|cx| service.poll_ready(cx)).await {
+            log::error!("Redis command service is failed: {:?}", e);
+            io.close();
+            return Ok(());
+        }
+
+        let quit = cmd.is("QUIT");
+        let protover = if cmd.is("HELLO") {
+            cmd.args().first().cloned()
+        } else {
+            None
+        };
+
+        match service.call(cmd).await {
+            Ok(reply) => {
+                if !matches!(reply, Response::Error(_)) {
+                    match protover.as_ref().map(|v| v.as_ref()) {
+                        Some(b"2") => codec.set_resp3(false),
+                        Some(b"3") => codec.set_resp3(true),
+                        _ => (),
+                    }
+                }
+                io.encode(reply, &codec)?;
+            }
+            Err(e) => {
+                log::error!("Redis command service error: {:?}", e);
+                io.close();
+                return Ok(());
+            }
+        }
+
+        if quit {
+            io.shutdown().await?;
+            return Ok(());
+        }
+    }
+}
@@ -1,36 +1,92 @@
-use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::{future::Future, pin::Pin, rc::Rc};
 
 use super::cmd::{
-    commands::{PubSubCommand, SubscribeOutputCommand},
+    self,
+    commands::{Commands, PubSubCommand, SubscribeOutputCommand},
     Command,
 };
-use super::codec::Codec;
+use super::codec::{BulkString, Codec, ReplyChunk, Request, Response};
+use super::codec::{StreamDecoder, StreamFrame};
 use super::errors::{CommandError, Error};
-use ntex::{io::IoBoxed, io::RecvError, util::poll_fn, util::ready, util::Stream};
+use super::observer::Outcome;
+use super::pipeline::Pipeline;
+use super::trace::{CommandSpan, Peer};
+use super::transaction::{Transaction, TransactionResult};
+use ntex::{codec::Decoder, io::IoBoxed, io::RecvError};
+use ntex::{util::poll_fn, util::ready, util::Stream};
 
 /// Redis client
 pub struct SimpleClient {
     io: IoBoxed,
+    peer: Rc<Peer>,
+    codec: Codec,
 }
 
 impl SimpleClient {
     /// Create new simple client
-    pub(crate) fn new(io: IoBoxed) -> Self {
-        SimpleClient { io }
+    pub(crate) fn new(io: IoBoxed, peer: Rc<Peer>, codec: Codec) -> Self {
+        SimpleClient { io, peer, codec }
     }
 
     /// Execute redis command and wait result
     pub async fn exec<U>(&self, cmd: U) -> Result<U::Output, CommandError>
+    where
+        U: Command,
+    {
+        let req = cmd.to_request();
+        let span = CommandSpan::new(&self.peer, &req);
+
+        span.clone()
+            .instrument(async move {
+                let item = match self.write(req) {
+                    Ok(()) => self.recv_response().await,
+                    Err(e) => Err(e),
+                };
+                span.finish(Outcome::from_result(
+                    item.as_ref().map(std::slice::from_ref),
+                ));
+                U::to_output(item?.into_result().map_err(CommandError::Error)?)
+            })
+            .await
+    }
+
+    /// Execute redis command and stream reply
+    ///
+    /// Bulk string reply is yielded as `ReplyChunk::Bytes` chunks as soon
+    /// as data is received, array reply is yielded element by element.
+    /// Any other reply, including nil, is yielded as single element and
+    /// redis server error is yielded as `CommandError::Error`. Stream must be
+    /// consumed to the end, otherwise connection gets closed.
+    ///
+    /// ```rust
+    /// use ntex_redis::{cmd, codec::ReplyChunk, RedisConnector};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let redis = RedisConnector::new("127.0.0.1:6379").connect_simple().await?;
+    ///     redis.exec(cmd::Set("blob", vec![0u8; 1024 * 1024])).await?;
+    ///
+    ///     let reply = redis.exec_stream(cmd::Get("blob"))?;
+    ///     let mut size = 0;
+    ///     while let Some(chunk) = reply.recv().await {
+    ///         if let ReplyChunk::Bytes(chunk) = chunk? {
+    ///             size += chunk.len();
+    ///         }
+    ///     }
+    ///     assert_eq!(size, 1024 * 1024);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn exec_stream<U>(&self, cmd: U) -> Result<ReplyStream<'_>, CommandError>
     where
         U: Command,
     {
         self.send(cmd)?;
-        loop {
-            if let Some(result) = self.recv::<U>().await {
-                return result;
-            }
-        }
+        Ok(ReplyStream {
+            client: self,
+            decoder: StreamDecoder::new(self.codec.clone()),
+        })
     }
 
     /// Send redis command
@@ -38,8 +94,121 @@ impl SimpleClient {
     where
         U: Command,
     {
-        self.io.encode(cmd.to_request(), &Codec)?;
-        Ok(())
+        self.write(cmd.to_request())
+    }
+
+    /// Execute pipeline of redis commands
+    ///
+    /// All commands are sent in one write. Redis server errors are reported
+    /// for each command individually, protocol errors fail the whole pipeline.
+    pub async fn pipeline<T>(&self, pipeline: Pipeline<T>) -> Result<T::Output, CommandError>
+    where
+        T: Commands,
+    {
+        let items = self.exec_batch(pipeline.into_requests()).await?;
+        Ok(T::to_output(&mut items.into_iter()))
+    }
+
+    /// Execute transaction
+    ///
+    /// Commands are wrapped with `MULTI` and `EXEC` and sent in one write.
+    pub async fn transaction<T>(
+        &self,
+        tx: Transaction<T>,
+    ) -> Result<TransactionResult<T::Output>, CommandError>
+    where
+        T: Commands,
+    {
+        Transaction::<T>::to_output(self.exec_batch(tx.into_requests()).await?)
+    }
+
+    /// Optimistic locking with `WATCH`
+    ///
+    /// Watches `keys` and executes transaction returned by `f`. If any of
+    /// the watched keys is modified before `EXEC`, whole cycle is repeated
+    /// up to `retries` times, after that `TransactionResult::Aborted` is
+    /// returned. If `f` fails, keys get unwatched and error is returned.
+    ///
+    /// ```rust
+    /// use ntex_redis::{cmd, RedisConnector, Transaction};
+    /// # use rand::{thread_rng, Rng, distributions::Alphanumeric};
+    /// # fn gen_random_key() -> String {
+    /// #    thread_rng().sample_iter(&Alphanumeric).take(12).map(char::from).collect::<String>()
+    /// # }
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let redis = RedisConnector::new("127.0.0.1:6379").connect_simple().await?;
+    ///     let key = gen_random_key();
+    ///
+    ///     // check-and-set
+    ///     let result = redis
+    ///         .watch(vec![&key], 3, || async {
+    ///             let value = redis.exec(cmd::Get(&key)).await?.unwrap_or_default();
+    ///             let mut value = value.to_vec();
+    ///             value.push(b'x');
+    ///             Ok(Transaction::new().add(cmd::Set(&key, value)))
+    ///         })
+    ///         .await?;
+    ///
+    ///     assert!(result.is_committed());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn watch<K, F, R, T>(
+        &self,
+        keys: K,
+        retries: usize,
+        mut f: F,
+    ) -> Result<TransactionResult<T::Output>, CommandError>
+    where
+        K: IntoIterator + Clone,
+        BulkString: From<K::Item>,
+        F: FnMut() -> R,
+        R: Future<Output = Result<Transaction<T>, CommandError>>,
+        T: Commands,
+    {
+        for _ in 0..=retries {
+            let mut keys = keys.clone().into_iter();
+            if let Some(key) = keys.next() {
+                self.exec(cmd::Watch(key).keys(keys)).await?;
+            }
+
+            let tx = match f().await {
+                Ok(tx) => tx,
+                Err(e) => {
+                    self.exec(cmd::Unwatch()).await?;
+                    return Err(e);
+                }
+            };
+            if let TransactionResult::Committed(res) = self.transaction(tx).await? {
+                return Ok(TransactionResult::Committed(res));
+            }
+        }
+        Ok(TransactionResult::Aborted)
+    }
+
+    async fn exec_batch(&self, reqs: Vec<Request>) -> Result<Vec<Response>, CommandError> {
+        let size = reqs.len();
+        let span = CommandSpan::batch(&self.peer, &reqs);
+
+        span.clone()
+            .instrument(async move {
+                let mut items = Vec::with_capacity(size);
+                let res = async {
+                    for req in reqs {
+                        self.write(req)?;
+                    }
+                    while items.len() < size {
+                        items.push(poll_fn(|cx| self.poll_response(cx)).await?);
+                    }
+                    Ok(())
+                }
+                .await;
+                span.finish(Outcome::from_result(res.as_ref().map(|_| &items[..])));
+                res.map(|_| items)
+            })
+            .await
     }
 
     /// Execute redis SUBSCRIBE command and act with output as stream
@@ -58,35 +227,135 @@ impl SimpleClient {
         self.io
     }
 
-    async fn recv<U: Command>(&self) -> Option<Result<U::Output, CommandError>> {
-        poll_fn(|cx| self.poll_recv::<U>(cx)).await
+    /// Connection details
+    pub(crate) fn peer(&self) -> &Rc<Peer> {
+        &self.peer
+    }
+
+    /// Send raw request
+    pub(crate) fn encode(&self, req: Request) -> Result<(), CommandError> {
+        self.write(req)
+    }
+
+    fn write(&self, req: Request) -> Result<(), CommandError> {
+        self.peer.log_request(&req);
+        self.io.encode(req, &self.codec)?;
+        Ok(())
+    }
+
+    /// Close underlying connection
+    pub(crate) fn close(&self) {
+        self.io.close()
+    }
+
+    /// Receive next response from connection
+    pub(crate) async fn recv_response(&self) -> Result<Response, CommandError> {
+        poll_fn(|cx| self.poll_response(cx)).await
     }
 
     fn poll_recv<U: Command>(
         &self,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<U::Output, CommandError>>> {
-        match ready!(self.io.poll_recv(&Codec, cx)) {
+        match ready!(self.poll_response(cx)) {
             Ok(item) => match item.into_result() {
                 Ok(result) => Poll::Ready(Some(U::to_output(result))),
                 Err(err) => Poll::Ready(Some(Err(CommandError::Error(err)))),
             },
-            Err(RecvError::KeepAlive) | Err(RecvError::Stop) => {
-                unreachable!()
-            }
-            Err(RecvError::WriteBackpressure) => {
-                if let Err(err) = ready!(self.io.poll_flush(cx, false))
-                    .map_err(|e| CommandError::Protocol(Error::PeerGone(Some(e))))
-                {
-                    Poll::Ready(Some(Err(err)))
-                } else {
-                    Poll::Pending
+            Err(err) => Poll::Ready(Some(Err(err))),
+        }
+    }
+
+    fn poll_response(&self, cx: &mut Context<'_>) -> Poll<Result<Response, CommandError>> {
+        let item = ready!(self.poll_decode(&self.codec, cx));
+        if let Ok(ref item) = item {
+            self.peer.log_response(item);
+        }
+        Poll::Ready(item)
+    }
+
+    fn poll_decode<U>(
+        &self,
+        codec: &U,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<U::Item, CommandError>>
+    where
+        U: Decoder<Error = Error>,
+    {
+        loop {
+            return match ready!(self.io.poll_recv(codec, cx)) {
+                Ok(item) => Poll::Ready(Ok(item)),
+                Err(RecvError::KeepAlive) | Err(RecvError::Stop) => {
+                    unreachable!()
                 }
+                Err(RecvError::WriteBackpressure) => {
+                    if let Err(err) = ready!(self.io.poll_flush(cx, false)) {
+                        Poll::Ready(Err(CommandError::Protocol(Error::PeerGone(Some(err)))))
+                    } else {
+                        continue;
+                    }
+                }
+                Err(RecvError::Decoder(err)) => {
+                    // stream position is lost, connection cannot be used
+                    self.io.close();
+                    Poll::Ready(Err(CommandError::Protocol(err)))
+                }
+                Err(RecvError::PeerGone(err)) => {
+                    Poll::Ready(Err(CommandError::Protocol(Error::PeerGone(err))))
+                }
+            };
+        }
+    }
+}
+
+/// Streamed redis reply
+pub struct ReplyStream<'a> {
+    client: &'a SimpleClient,
+    decoder: StreamDecoder,
+}
+
+impl<'a> ReplyStream<'a> {
+    /// Receive next part of the reply
+    pub async fn recv(&self) -> Option<Result<ReplyChunk, CommandError>> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Attempt to pull out the next part of the reply, registering
+    /// the current task for wakeup if the part is not yet available,
+    /// and returning None if the reply is exhausted.
+    pub fn poll_recv(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<ReplyChunk, CommandError>>> {
+        if self.decoder.is_done() {
+            return Poll::Ready(None);
+        }
+        match ready!(self.client.poll_decode(&self.decoder, cx)) {
+            Ok(StreamFrame::Chunk(chunk)) => Poll::Ready(Some(Ok(chunk))),
+            Ok(StreamFrame::Error(err)) => Poll::Ready(Some(Err(CommandError::Error(err)))),
+            Ok(StreamFrame::End) => Poll::Ready(None),
+            Err(err) => {
+                self.decoder.stop();
+                self.client.close();
+                Poll::Ready(Some(Err(err)))
             }
-            Err(RecvError::Decoder(err)) => Poll::Ready(Some(Err(CommandError::Protocol(err)))),
-            Err(RecvError::PeerGone(err)) => {
-                Poll::Ready(Some(Err(CommandError::Protocol(Error::PeerGone(err)))))
-            }
+        }
+    }
+}
+
+impl<'a> Stream for ReplyStream<'a> {
+    type Item = Result<ReplyChunk, CommandError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_recv(cx)
+    }
+}
+
+impl<'a> Drop for ReplyStream<'a> {
+    fn drop(&mut self) {
+        // rest of the reply cannot be skipped without reading it
+        if !self.decoder.is_done() {
+            self.client.close();
         }
     }
 }
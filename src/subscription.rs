@@ -0,0 +1,271 @@
+use std::{cell::RefCell, convert::TryFrom, pin::Pin, rc::Rc, task::Context, task::Poll};
+
+use ntex::channel::mpsc;
+use ntex::util::{poll_fn, Bytes, HashMap, Stream};
+
+use super::cmd::SubscribeItem;
+use super::codec::{Request, Response};
+use super::errors::CommandError;
+use super::Client;
+
+type Item = Result<SubscribeItem, CommandError>;
+
+/// Subscription kind and channel name or pattern
+type Key = (Kind, Bytes);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Kind {
+    Channel,
+    Pattern,
+    Shard,
+}
+
+impl Kind {
+    fn from_command(name: &[u8]) -> Option<Kind> {
+        if name.eq_ignore_ascii_case(b"SUBSCRIBE") {
+            Some(Kind::Channel)
+        } else if name.eq_ignore_ascii_case(b"PSUBSCRIBE") {
+            Some(Kind::Pattern)
+        } else if name.eq_ignore_ascii_case(b"SSUBSCRIBE") {
+            Some(Kind::Shard)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn subscribe(self) -> &'static str {
+        match self {
+            Kind::Channel => "SUBSCRIBE",
+            Kind::Pattern => "PSUBSCRIBE",
+            Kind::Shard => "SSUBSCRIBE",
+        }
+    }
+
+    pub(crate) fn unsubscribe(self) -> &'static str {
+        match self {
+            Kind::Channel => "UNSUBSCRIBE",
+            Kind::Pattern => "PUNSUBSCRIBE",
+            Kind::Shard => "SUNSUBSCRIBE",
+        }
+    }
+}
+
+/// Subscriptions registered on shared client
+#[derive(Default)]
+pub(crate) struct Subscriptions {
+    senders: HashMap<Key, Vec<(usize, mpsc::Sender<Item>)>>,
+    next_id: usize,
+}
+
+impl Subscriptions {
+    /// Route push message to subscription streams
+    ///
+    /// Returns false if message is not pubsub message.
+    pub(crate) fn route(&mut self, item: &[Response]) -> bool {
+        let name = match item.first() {
+            Some(Response::Bytes(name)) => name.as_ref(),
+            Some(Response::String(name)) => name.as_bytes(),
+            _ => return false,
+        };
+        let kind = match name {
+            b"message" | b"subscribe" | b"unsubscribe" => Kind::Channel,
+            b"pmessage" | b"psubscribe" | b"punsubscribe" => Kind::Pattern,
+            b"smessage" | b"ssubscribe" | b"sunsubscribe" => Kind::Shard,
+            _ => return false,
+        };
+        // channel name, or pattern for pattern subscriptions
+        let channel = match item.get(1) {
+            Some(Response::Bytes(channel)) => channel.clone(),
+            Some(Response::String(channel)) => channel.clone().into_bytes(),
+            _ => return false,
+        };
+
+        let key = (kind, channel);
+        if let Some(senders) = self.senders.get_mut(&key) {
+            let msg = SubscribeItem::try_from(Response::Array(item.to_vec()));
+            senders.retain(|(_, tx)| tx.send(msg.clone()).is_ok());
+        }
+        true
+    }
+
+    /// Close all subscription streams
+    pub(crate) fn clear(&mut self) {
+        self.senders.clear();
+    }
+
+    fn register(&mut self, keys: &[Key], tx: mpsc::Sender<Item>) -> usize {
+        self.next_id += 1;
+        for key in keys {
+            self.senders
+                .entry(key.clone())
+                .or_default()
+                .push((self.next_id, tx.clone()));
+        }
+        self.next_id
+    }
+
+    /// Remove subscription, returns channels without subscribers
+    fn remove(&mut self, id: usize, keys: &[Key]) -> Vec<Key> {
+        let mut unused = Vec::new();
+        for key in keys {
+            if let Some(senders) = self.senders.get_mut(key) {
+                senders.retain(|(sid, _)| *sid != id);
+                if senders.is_empty() {
+                    self.senders.remove(key);
+                    unused.push(key.clone());
+                }
+            }
+        }
+        unused
+    }
+}
+
+/// Subscription on shared client
+///
+/// Stream of subscription confirmations and messages for subscribed
+/// channels. Channels get unsubscribed when last subscription for
+/// a channel is dropped.
+pub struct Subscription {
+    id: usize,
+    keys: Vec<Key>,
+    client: Client,
+    rx: mpsc::Receiver<Item>,
+}
+
+impl Subscription {
+    pub(crate) fn new(client: &Client, req: Request) -> Result<Self, CommandError> {
+        let args = match req {
+            Request::Array(ref args) => args,
+            _ => {
+                return Err(CommandError::Output(
+                    "Not a subscribe command",
+                    Response::Nil,
+                ))
+            }
+        };
+        let kind = args
+            .first()
+            .and_then(|name| name.to_bytes())
+            .and_then(|name| Kind::from_command(&name))
+            .ok_or(CommandError::Output(
+                "Not a subscribe command",
+                Response::Nil,
+            ))?;
+        let keys: Vec<_> = args[1..]
+            .iter()
+            .filter_map(|ch| ch.to_bytes().map(|ch| (kind, ch)))
+            .collect();
+
+        let (tx, rx) = mpsc::channel();
+        let id = client.subscriptions().borrow_mut().register(&keys, tx);
+        let subscription = Subscription {
+            id,
+            keys,
+            rx,
+            client: client.clone(),
+        };
+        client.encode(req)?;
+        Ok(subscription)
+    }
+
+    /// Receive next subscription item
+    pub async fn recv(&self) -> Option<Result<SubscribeItem, CommandError>> {
+        poll_fn(|cx| self.rx.poll_recv(cx)).await
+    }
+
+    /// Attempt to pull out the next value of this stream, registering
+    /// the current task for wakeup if the value is not yet available,
+    /// and returning None if connection is closed.
+    pub fn poll_recv(
+        &self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<SubscribeItem, CommandError>>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Result<SubscribeItem, CommandError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let unused = self
+            .client
+            .subscriptions()
+            .borrow_mut()
+            .remove(self.id, &self.keys);
+        if unused.is_empty() || !self.client.is_connected() {
+            return;
+        }
+
+        for kind in &[Kind::Channel, Kind::Pattern, Kind::Shard] {
+            let channels: Vec<_> = unused
+                .iter()
+                .filter(|(k, _)| k == kind)
+                .map(|(_, ch)| Request::from(ch))
+                .collect();
+            if !channels.is_empty() {
+                let req = Request::from_static(kind.unsubscribe()).extend(channels);
+                if let Err(e) = self.client.encode(req) {
+                    log::warn!("Cannot unsubscribe: {}", e);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) type SharedSubscriptions = Rc<RefCell<Subscriptions>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(items: &[&'static str]) -> Vec<Response> {
+        items
+            .iter()
+            .map(|s| Response::Bytes(Bytes::from_static(s.as_bytes())))
+            .collect()
+    }
+
+    #[ntex::test]
+    async fn test_route() {
+        let mut subs = Subscriptions::default();
+        let (tx, rx) = mpsc::channel();
+        let keys = vec![
+            (Kind::Channel, Bytes::from_static(b"news")),
+            (Kind::Pattern, Bytes::from_static(b"news.*")),
+        ];
+        let id = subs.register(&keys, tx);
+
+        assert!(subs.route(&push(&["message", "news", "hello"])));
+        assert!(subs.route(&push(&["pmessage", "news.*", "news.it", "hi"])));
+        assert!(subs.route(&push(&["message", "other", "skipped"])));
+        assert!(!subs.route(&push(&["invalidate", "key"])));
+
+        assert_eq!(
+            rx.recv().await.unwrap().unwrap(),
+            SubscribeItem::Message {
+                pattern: None,
+                channel: Bytes::from_static(b"news"),
+                payload: Bytes::from_static(b"hello"),
+            }
+        );
+        assert_eq!(
+            rx.recv().await.unwrap().unwrap(),
+            SubscribeItem::Message {
+                pattern: Some(Bytes::from_static(b"news.*")),
+                channel: Bytes::from_static(b"news.it"),
+                payload: Bytes::from_static(b"hi"),
+            }
+        );
+
+        assert_eq!(subs.remove(id, &keys), keys);
+        assert!(rx.recv().await.is_none());
+        assert!(subs.senders.is_empty());
+    }
+}
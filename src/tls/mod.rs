@@ -0,0 +1,87 @@
+//! TLS transport connectors
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "openssl")]
+mod openssl;
+#[cfg(feature = "rustls")]
+mod rustls;
+
+#[cfg(feature = "openssl")]
+pub use self::openssl::OpensslConnector;
+#[cfg(feature = "rustls")]
+pub use self::rustls::RustlsConnector;
+
+/// TLS connection options
+///
+/// By default server certificate is verified against system (openssl)
+/// or bundled webpki (rustls) root certificates, and connect host is
+/// used as a server name.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    server_name: Option<String>,
+    ca_file: Option<PathBuf>,
+    client_cert: Option<(PathBuf, PathBuf)>,
+    insecure: bool,
+}
+
+impl TlsConfig {
+    /// Create default TLS options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set server name for SNI and certificate verification
+    pub fn server_name<U: AsRef<str>>(mut self, name: U) -> Self {
+        self.server_name = Some(name.as_ref().to_string());
+        self
+    }
+
+    /// Verify server certificate with CA certificates from PEM bundle
+    pub fn ca_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.ca_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Use client certificate chain and private key from PEM files
+    /// for mutual TLS
+    pub fn client_cert<P: AsRef<Path>, K: AsRef<Path>>(mut self, cert: P, key: K) -> Self {
+        self.client_cert = Some((cert.as_ref().to_path_buf(), key.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Do not verify server certificate and server name
+    ///
+    /// Connection is open to man-in-the-middle attacks, use it
+    /// for local testing only.
+    pub fn danger_accept_invalid_certs(mut self) -> Self {
+        self.insecure = true;
+        self
+    }
+}
+
+/// Default server name, connect host without port and ipv6 brackets
+pub(crate) fn server_name(host: &str) -> &str {
+    let name = match host.rsplit_once(':') {
+        // bare ipv6 address has no port
+        Some((name, _)) if !host.starts_with('[') && name.contains(':') => host,
+        Some((name, _)) if !host.starts_with('[') || name.ends_with(']') => name,
+        _ => host,
+    };
+    name.trim_start_matches('[').trim_end_matches(']')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_name() {
+        assert_eq!(server_name("redis.example.com"), "redis.example.com");
+        assert_eq!(server_name("redis.example.com:6380"), "redis.example.com");
+        assert_eq!(server_name("127.0.0.1:6380"), "127.0.0.1");
+        assert_eq!(server_name("[::1]:6380"), "::1");
+        assert_eq!(server_name("[::1]"), "::1");
+        assert_eq!(server_name("[fe80::1:2]:6380"), "fe80::1:2");
+        assert_eq!(server_name("::1"), "::1");
+    }
+}
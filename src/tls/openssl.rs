@@ -0,0 +1,137 @@
+use std::{future::Future, io, pin::Pin};
+
+use ntex::connect::{Address, Connect, ConnectError, Connector};
+use ntex::io::{FilterFactory, Io, Layer};
+use ntex::service::Service;
+use ntex::tls::openssl::{SslConnector as IoSslConnector, SslFilter};
+use tls_openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode};
+
+use super::TlsConfig;
+use crate::RedisConnector;
+
+/// Openssl transport connector
+pub struct OpensslConnector<A> {
+    connector: Connector<A>,
+    ssl: SslConnector,
+    server_name: Option<String>,
+    insecure: bool,
+}
+
+impl<A> OpensslConnector<A> {
+    /// Create connector from TLS options
+    pub fn new(config: &TlsConfig) -> io::Result<Self> {
+        let mut builder = SslConnector::builder(SslMethod::tls())?;
+        if let Some(ref path) = config.ca_file {
+            builder.set_ca_file(path)?;
+        }
+        if let Some((ref cert, ref key)) = config.client_cert {
+            builder.set_certificate_chain_file(cert)?;
+            builder.set_private_key_file(key, SslFiletype::PEM)?;
+            builder.check_private_key()?;
+        }
+        if config.insecure {
+            builder.set_verify(SslVerifyMode::NONE);
+        }
+        Ok(OpensslConnector {
+            connector: Connector::default(),
+            ssl: builder.build(),
+            server_name: config.server_name.clone(),
+            insecure: config.insecure,
+        })
+    }
+
+    /// Create connector from configured openssl connector
+    pub fn from_connector(ssl: SslConnector) -> Self {
+        OpensslConnector {
+            ssl,
+            connector: Connector::default(),
+            server_name: None,
+            insecure: false,
+        }
+    }
+}
+
+impl<A> Clone for OpensslConnector<A> {
+    fn clone(&self) -> Self {
+        OpensslConnector {
+            connector: self.connector.clone(),
+            ssl: self.ssl.clone(),
+            server_name: self.server_name.clone(),
+            insecure: self.insecure,
+        }
+    }
+}
+
+impl<A: Address> Service<Connect<A>> for OpensslConnector<A> {
+    type Response = Io<Layer<SslFilter>>;
+    type Error = ConnectError;
+    type Future<'f> = Pin<Box<dyn Future<Output = Result<Self::Response, ConnectError>> + 'f>>;
+
+    fn call(&self, req: Connect<A>) -> Self::Future<'_> {
+        let host = self
+            .server_name
+            .clone()
+            .unwrap_or_else(|| super::server_name(req.host()).to_string());
+
+        Box::pin(async move {
+            let io = self.connector.call(req).await?;
+
+            let mut config = self
+                .ssl
+                .configure()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            if self.insecure {
+                config.set_verify_hostname(false);
+            }
+            let ssl = config
+                .into_ssl(&host)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+            match IoSslConnector::new(ssl).create(io).await {
+                Ok(io) => Ok(io),
+                Err(e) => {
+                    log::trace!("SSL Handshake error: {:?}", e);
+                    Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}", e)).into())
+                }
+            }
+        })
+    }
+}
+
+impl<A> RedisConnector<A, ()>
+where
+    A: Address + Clone,
+{
+    /// Create redis connector with openssl transport
+    pub fn openssl(
+        address: A,
+        config: &TlsConfig,
+    ) -> io::Result<RedisConnector<A, OpensslConnector<A>>> {
+        Ok(RedisConnector::new(address).connector(OpensslConnector::new(config)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let connector = OpensslConnector::<String>::new(
+            &TlsConfig::new()
+                .server_name("redis.local")
+                .danger_accept_invalid_certs(),
+        )
+        .unwrap();
+        assert_eq!(connector.server_name.as_deref(), Some("redis.local"));
+        assert!(connector.insecure);
+
+        assert!(
+            OpensslConnector::<String>::new(&TlsConfig::new().ca_file("/not/exists")).is_err()
+        );
+        assert!(OpensslConnector::<String>::new(
+            &TlsConfig::new().client_cert("/not/exists", "/not/exists")
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use std::{convert::TryFrom, fs::File, future::Future, io, io::BufReader, path::Path};
+use std::{pin::Pin, sync::Arc, time::SystemTime};
+
+use ntex::connect::{Address, Connect, ConnectError, Connector};
+use ntex::io::{FilterFactory, Io, Layer};
+use ntex::service::Service;
+use ntex::tls::rustls::{TlsConnector, TlsFilter};
+use tls_rustls::client::{ServerCertVerified, ServerCertVerifier};
+use tls_rustls::{Certificate, ClientConfig, OwnedTrustAnchor, PrivateKey};
+use tls_rustls::{RootCertStore, ServerName};
+
+use super::TlsConfig;
+use crate::RedisConnector;
+
+/// Rustls transport connector
+pub struct RustlsConnector<A> {
+    connector: Connector<A>,
+    config: Arc<ClientConfig>,
+    server_name: Option<String>,
+}
+
+impl<A> RustlsConnector<A> {
+    /// Create connector from TLS options
+    pub fn new(config: &TlsConfig) -> io::Result<Self> {
+        let mut roots = RootCertStore::empty();
+        if let Some(ref path) = config.ca_file {
+            for cert in rustls_pemfile::certs(&mut reader(path)?)? {
+                roots
+                    .add(&Certificate(cert))
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            }
+        } else {
+            roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+                OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject,
+                    ta.spki,
+                    ta.name_constraints,
+                )
+            }));
+        }
+
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+        let mut tls = if let Some((ref cert, ref key)) = config.client_cert {
+            let certs = rustls_pemfile::certs(&mut reader(cert)?)?
+                .into_iter()
+                .map(Certificate)
+                .collect();
+            builder
+                .with_single_cert(certs, private_key(key)?)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        } else {
+            builder.with_no_client_auth()
+        };
+        if config.insecure {
+            tls.dangerous()
+                .set_certificate_verifier(Arc::new(NoCertificateVerification));
+        }
+
+        Ok(RustlsConnector {
+            connector: Connector::default(),
+            config: Arc::new(tls),
+            server_name: config.server_name.clone(),
+        })
+    }
+
+    /// Create connector from rustls client config
+    pub fn from_config(config: Arc<ClientConfig>) -> Self {
+        RustlsConnector {
+            config,
+            connector: Connector::default(),
+            server_name: None,
+        }
+    }
+}
+
+impl<A> Clone for RustlsConnector<A> {
+    fn clone(&self) -> Self {
+        RustlsConnector {
+            connector: self.connector.clone(),
+            config: self.config.clone(),
+            server_name: self.server_name.clone(),
+        }
+    }
+}
+
+impl<A: Address> Service<Connect<A>> for RustlsConnector<A> {
+    type Response = Io<Layer<TlsFilter>>;
+    type Error = ConnectError;
+    type Future<'f> = Pin<Box<dyn Future<Output = Result<Self::Response, ConnectError>> + 'f>>;
+
+    fn call(&self, req: Connect<A>) -> Self::Future<'_> {
+        let host = self
+            .server_name
+            .clone()
+            .unwrap_or_else(|| super::server_name(req.host()).to_string());
+
+        Box::pin(async move {
+            let host = ServerName::try_from(host.as_str())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{}", e)))?;
+            let io = self.connector.call(req).await?;
+
+            match TlsConnector::new(self.config.clone())
+                .server_name(host)
+                .create(io)
+                .await
+            {
+                Ok(io) => Ok(io),
+                Err(e) => {
+                    log::trace!("TLS Handshake error: {:?}", e);
+                    Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}", e)).into())
+                }
+            }
+        })
+    }
+}
+
+impl<A> RedisConnector<A, ()>
+where
+    A: Address + Clone,
+{
+    /// Create redis connector with rustls transport
+    pub fn rustls(
+        address: A,
+        config: &TlsConfig,
+    ) -> io::Result<RedisConnector<A, RustlsConnector<A>>> {
+        Ok(RedisConnector::new(address).connector(RustlsConnector::new(config)?))
+    }
+}
+
+fn reader(path: &Path) -> io::Result<BufReader<File>> {
+    File::open(path).map(BufReader::new)
+}
+
+/// Load first private key from PEM file
+fn private_key(path: &Path) -> io::Result<PrivateKey> {
+    for item in rustls_pemfile::read_all(&mut reader(path)?)? {
+        match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => (),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Private key is not found",
+    ))
+}
+
+/// Accepts any server certificate
+struct NoCertificateVerification;
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _: &Certificate,
+        _: &[Certificate],
+        _: &ServerName,
+        _: &mut dyn Iterator<Item = &[u8]>,
+        _: &[u8],
+        _: SystemTime,
+    ) -> Result<ServerCertVerified, tls_rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let connector = RustlsConnector::<String>::new(
+            &TlsConfig::new()
+                .server_name("redis.local")
+                .danger_accept_invalid_certs(),
+        )
+        .unwrap();
+        assert_eq!(connector.server_name.as_deref(), Some("redis.local"));
+
+        assert!(RustlsConnector::<String>::new(&TlsConfig::new().ca_file("/not/exists")).is_err());
+        assert!(RustlsConnector::<String>::new(
+            &TlsConfig::new().client_cert("/not/exists", "/not/exists")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_server_name() {
+        for host in ["redis.local:6380", "127.0.0.1:6380", "[::1]:6380", "[::1]"] {
+            assert!(ServerName::try_from(crate::tls::server_name(host)).is_ok());
+        }
+    }
+}
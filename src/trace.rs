@@ -0,0 +1,458 @@
+//! Command tracing and wire-level logging
+use std::{fmt, future::Future};
+
+use ntex::util::Bytes;
+
+use super::codec::{Request, Response};
+use super::observer::Outcome;
+
+/// Connection details for command spans and wire log
+#[derive(Debug, Default)]
+pub(crate) struct Peer {
+    /// Redis server address
+    pub(crate) address: String,
+    /// Database selected on connect
+    pub(crate) db: u32,
+    /// Log encoded requests and decoded responses
+    pub(crate) log_wire: bool,
+}
+
+impl Peer {
+    /// Log encoded request
+    pub(crate) fn log_request(&self, req: &Request) {
+        if self.log_wire {
+            log::debug!("{}/{} > {}", self.address, self.db, CliRequest(req));
+        }
+    }
+
+    /// Log decoded response
+    pub(crate) fn log_response(&self, item: &Response) {
+        if self.log_wire {
+            log::debug!("{}/{} < {}", self.address, self.db, CliResponse(item));
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[derive(Clone)]
+/// Command span
+pub(crate) struct CommandSpan {
+    span: tracing::Span,
+    started: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl CommandSpan {
+    pub(crate) fn new(peer: &Peer, req: &Request) -> Self {
+        Self::batch(peer, std::slice::from_ref(req))
+    }
+
+    /// Span for batch of commands, command name and key are taken
+    /// from the first command
+    ///
+    /// Selected database is not recorded, it could be changed with `SELECT`.
+    pub(crate) fn batch(peer: &Peer, reqs: &[Request]) -> Self {
+        use tracing::field::{display, Empty};
+
+        let span = tracing::debug_span!(
+            "redis",
+            command = Empty,
+            key = Empty,
+            commands = reqs.len(),
+            peer = %peer.address,
+            latency_ms = Empty,
+            outcome = Empty,
+        );
+        if !span.is_disabled() {
+            if let Some(Request::Array(args)) = reqs.first() {
+                if let Some(name) = args.first().and_then(|name| name.to_bytes()) {
+                    span.record("command", display(String::from_utf8_lossy(&name)));
+                }
+            }
+            if let Some(key) = reqs.first().and_then(crate::cluster::request_key) {
+                span.record("key", display(String::from_utf8_lossy(&key)));
+            }
+        }
+        CommandSpan {
+            span,
+            started: ntex::time::now(),
+        }
+    }
+
+    /// Record command latency and outcome
+    pub(crate) fn finish(&self, outcome: Outcome) {
+        let latency = ntex::time::now().saturating_duration_since(self.started);
+        self.span
+            .record("latency_ms", latency.as_secs_f64() * 1000.0)
+            .record("outcome", outcome.as_str());
+    }
+
+    /// Run future within the span
+    pub(crate) fn instrument<F: Future>(self, fut: F) -> impl Future<Output = F::Output> {
+        tracing::Instrument::instrument(fut, self.span)
+    }
+
+    /// Run closure within the span
+    pub(crate) fn in_scope<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        self.span.in_scope(f)
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+#[derive(Clone)]
+/// Command span, enabled with `tracing` feature
+pub(crate) struct CommandSpan;
+
+#[cfg(not(feature = "tracing"))]
+impl CommandSpan {
+    pub(crate) fn new(_: &Peer, _: &Request) -> Self {
+        CommandSpan
+    }
+
+    pub(crate) fn batch(_: &Peer, _: &[Request]) -> Self {
+        CommandSpan
+    }
+
+    pub(crate) fn finish(&self, _: Outcome) {}
+
+    pub(crate) fn instrument<F: Future>(self, fut: F) -> impl Future<Output = F::Output> {
+        fut
+    }
+
+    pub(crate) fn in_scope<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        f()
+    }
+}
+
+/// Request in redis-cli notation, credentials are redacted
+pub(crate) struct CliRequest<'a>(pub(crate) &'a Request);
+
+impl<'a> fmt::Display for CliRequest<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Request::Array(args) => {
+                for (idx, arg) in args.iter().enumerate() {
+                    if idx != 0 {
+                        f.write_str(" ")?;
+                    }
+                    if is_sensitive(args, idx) {
+                        f.write_str("(redacted)")?;
+                    } else {
+                        CliRequest(arg).fmt(f)?;
+                    }
+                }
+                Ok(())
+            }
+            Request::Integer(val) => write!(f, "{}", val),
+            req => write_quoted(f, &req.to_bytes().unwrap_or_default()),
+        }
+    }
+}
+
+/// Response in redis-cli notation
+pub(crate) struct CliResponse<'a>(pub(crate) &'a Response);
+
+impl<'a> fmt::Display for CliResponse<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_response(f, self.0, 0)
+    }
+}
+
+fn write_response(f: &mut fmt::Formatter<'_>, item: &Response, indent: usize) -> fmt::Result {
+    match item {
+        Response::Nil => f.write_str("(nil)"),
+        Response::String(val) => f.write_str(val),
+        Response::Error(val) => write!(f, "(error) {}", val),
+        Response::Integer(val) => write!(f, "(integer) {}", val),
+        Response::Double(val) => write!(f, "(double) {}", val.0),
+        Response::Boolean(val) => write!(f, "({})", val),
+        Response::BigNumber(val) => write!(f, "(big number) {}", val),
+        Response::Bytes(val) | Response::Verbatim(_, val) => write_quoted(f, val),
+        Response::Array(items) | Response::Push(items) => write_items(
+            f,
+            items.len(),
+            ')',
+            "(empty array)",
+            indent,
+            |f, idx, indent| write_response(f, &items[idx], indent),
+        ),
+        Response::Set(items) => write_items(
+            f,
+            items.len(),
+            '~',
+            "(empty set)",
+            indent,
+            |f, idx, indent| write_response(f, &items[idx], indent),
+        ),
+        Response::Map(items) => write_map(f, items, indent),
+        Response::Attribute(attrs, item) => {
+            f.write_str("|")?;
+            write_map(f, attrs, indent + 1)?;
+            write!(f, "\n{:indent$}", "", indent = indent)?;
+            write_response(f, item, indent)
+        }
+    }
+}
+
+fn write_map(
+    f: &mut fmt::Formatter<'_>,
+    items: &[(Response, Response)],
+    indent: usize,
+) -> fmt::Result {
+    write_items(
+        f,
+        items.len(),
+        '#',
+        "(empty hash)",
+        indent,
+        |f, idx, indent| {
+            write_response(f, &items[idx].0, indent)?;
+            f.write_str(" => ")?;
+            write_response(f, &items[idx].1, indent)
+        },
+    )
+}
+
+/// Write numbered elements, nested elements are aligned with parent element
+fn write_items<F>(
+    f: &mut fmt::Formatter<'_>,
+    len: usize,
+    sep: char,
+    empty: &str,
+    indent: usize,
+    item: F,
+) -> fmt::Result
+where
+    F: Fn(&mut fmt::Formatter<'_>, usize, usize) -> fmt::Result,
+{
+    if len == 0 {
+        return f.write_str(empty);
+    }
+    let width = len.to_string().len();
+    for idx in 0..len {
+        if idx != 0 {
+            write!(f, "\n{:indent$}", "", indent = indent)?;
+        }
+        write!(f, "{:>width$}{} ", idx + 1, sep, width = width)?;
+        item(f, idx, indent + width + 2)?;
+    }
+    Ok(())
+}
+
+/// Write bytes as quoted string, non-printable bytes are escaped
+fn write_quoted(f: &mut fmt::Formatter<'_>, val: &Bytes) -> fmt::Result {
+    f.write_str("\"")?;
+    for &b in val.iter() {
+        match b {
+            b'\\' => f.write_str("\\\\")?,
+            b'"' => f.write_str("\\\"")?,
+            b'\n' => f.write_str("\\n")?,
+            b'\r' => f.write_str("\\r")?,
+            b'\t' => f.write_str("\\t")?,
+            0x07 => f.write_str("\\a")?,
+            0x08 => f.write_str("\\b")?,
+            b if b.is_ascii_graphic() || b == b' ' => write!(f, "{}", b as char)?,
+            b => write!(f, "\\x{:02x}", b)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Check if request argument contains credentials
+fn is_sensitive(args: &[Request], idx: usize) -> bool {
+    let arg = |idx: usize| args.get(idx).and_then(|arg| arg.to_bytes());
+    let is = |idx: Option<usize>, name: &str| {
+        idx.and_then(arg)
+            .map(|arg| arg.eq_ignore_ascii_case(name.as_bytes()))
+            .unwrap_or(false)
+    };
+    let name = match arg(0) {
+        Some(name) => name,
+        None => return false,
+    };
+
+    if name.eq_ignore_ascii_case(b"AUTH") {
+        // AUTH [username] password
+        idx > 0 && idx == args.len() - 1
+    } else if name.eq_ignore_ascii_case(b"HELLO") {
+        // HELLO protover AUTH username password
+        is(idx.checked_sub(2), "AUTH")
+    } else if name.eq_ignore_ascii_case(b"MIGRATE") {
+        // MIGRATE ... AUTH password | AUTH2 username password
+        is(idx.checked_sub(1), "AUTH") || is(idx.checked_sub(2), "AUTH2")
+    } else if name.eq_ignore_ascii_case(b"CONFIG") {
+        // CONFIG SET requirepass password
+        is(Some(1), "SET")
+            && idx > 2
+            && (is(Some(idx - 1), "requirepass") || is(Some(idx - 1), "masterauth"))
+    } else if name.eq_ignore_ascii_case(b"ACL") {
+        // ACL SETUSER username >password <password #hash !hash
+        is(Some(1), "SETUSER")
+            && idx > 2
+            && matches!(
+                arg(idx).and_then(|arg| arg.first().copied()),
+                Some(b'>' | b'<' | b'#' | b'!')
+            )
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{array, cmd, cmd::Command, codec::Double};
+
+    fn cli(req: Request) -> String {
+        CliRequest(&req).to_string()
+    }
+
+    #[test]
+    fn test_request() {
+        assert_eq!(
+            cli(array!["SET", "key", "va\"l\r\n\x01"]),
+            "\"SET\" \"key\" \"va\\\"l\\r\\n\\x01\""
+        );
+        assert_eq!(cli(cmd::Select(1).to_request()), "\"SELECT\" \"1\"");
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(cli(cmd::Auth("secret").to_request()), "\"AUTH\" (redacted)");
+        assert_eq!(
+            cli(cmd::Auth("secret").username("user").to_request()),
+            "\"AUTH\" \"user\" (redacted)"
+        );
+        assert_eq!(
+            cli(cmd::Hello(3)
+                .auth("user", "secret")
+                .setname("app")
+                .to_request()),
+            "\"HELLO\" \"3\" \"AUTH\" \"user\" (redacted) \"SETNAME\" \"app\""
+        );
+        assert_eq!(
+            cli(array![
+                "MIGRATE", "host", "6379", "key", "0", "10", "AUTH2", "user", "secret"
+            ]),
+            "\"MIGRATE\" \"host\" \"6379\" \"key\" \"0\" \"10\" \"AUTH2\" \"user\" (redacted)"
+        );
+        assert_eq!(
+            cli(array!["config", "set", "requirepass", "secret"]),
+            "\"config\" \"set\" \"requirepass\" (redacted)"
+        );
+        assert_eq!(
+            cli(array!["ACL", "SETUSER", "user", "on", ">secret", "~*"]),
+            "\"ACL\" \"SETUSER\" \"user\" \"on\" (redacted) \"~*\""
+        );
+        assert_eq!(cli(array!["GET", "AUTH"]), "\"GET\" \"AUTH\"");
+    }
+
+    #[test]
+    fn test_response() {
+        let cli = |item: Response| CliResponse(&item).to_string();
+
+        assert_eq!(cli(Response::String("OK".into())), "OK");
+        assert_eq!(cli(Response::Nil), "(nil)");
+        assert_eq!(cli(Response::Integer(10)), "(integer) 10");
+        assert_eq!(cli(Response::Double(Double(1.5))), "(double) 1.5");
+        assert_eq!(cli(Response::Boolean(true)), "(true)");
+        assert_eq!(cli(Response::Error("ERR fail".into())), "(error) ERR fail");
+        assert_eq!(cli(Response::Array(vec![])), "(empty array)");
+        assert_eq!(
+            cli(Response::Array(vec![
+                Response::Bytes(Bytes::from_static(b"a")),
+                Response::Array(vec![Response::Integer(1), Response::Nil]),
+            ])),
+            "1) \"a\"\n2) 1) (integer) 1\n   2) (nil)"
+        );
+        assert_eq!(
+            cli(Response::Map(vec![(
+                Response::String("proto".into()),
+                Response::Integer(3)
+            )])),
+            "1# proto => (integer) 3"
+        );
+        let items = (0..10).map(Response::Integer).collect::<Vec<_>>();
+        assert!(cli(Response::Set(items)).starts_with(" 1~ (integer) 0\n 2~ "));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_span() {
+        use std::sync::{Arc, Mutex};
+        use tracing::{field, span, Event, Metadata};
+
+        type Fields = Vec<(&'static str, String)>;
+
+        #[derive(Clone, Default)]
+        struct Spans(Arc<Mutex<Vec<Fields>>>);
+
+        struct Visitor<'a>(&'a mut Fields);
+
+        impl<'a> field::Visit for Visitor<'a> {
+            fn record_debug(&mut self, field: &field::Field, value: &dyn fmt::Debug) {
+                self.0.push((field.name(), format!("{:?}", value)));
+            }
+
+            fn record_str(&mut self, field: &field::Field, value: &str) {
+                self.0.push((field.name(), value.to_string()));
+            }
+        }
+
+        impl tracing::Subscriber for Spans {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+                let mut spans = self.0.lock().unwrap();
+                let mut fields = Vec::new();
+                attrs.record(&mut Visitor(&mut fields));
+                spans.push(fields);
+                span::Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, id: &span::Id, values: &span::Record<'_>) {
+                let mut spans = self.0.lock().unwrap();
+                values.record(&mut Visitor(&mut spans[id.into_u64() as usize - 1]));
+            }
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let spans = Spans::default();
+        let peer = Peer {
+            address: "127.0.0.1:6379".to_string(),
+            ..Default::default()
+        };
+        tracing::subscriber::with_default(spans.clone(), || {
+            let span = CommandSpan::new(&peer, &cmd::Get("key").to_request());
+            span.finish(Outcome::Success);
+            let reqs = [
+                cmd::Set("key", "1").to_request(),
+                cmd::Get("key").to_request(),
+            ];
+            let span = CommandSpan::batch(&peer, &reqs);
+            span.finish(Outcome::Timeout);
+        });
+
+        let get = |idx: usize, name: &str| {
+            spans.0.lock().unwrap()[idx]
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(get(0, "command").as_deref(), Some("GET"));
+        assert_eq!(get(0, "key").as_deref(), Some("key"));
+        assert_eq!(get(0, "commands").as_deref(), Some("1"));
+        assert_eq!(get(0, "outcome").as_deref(), Some("success"));
+        assert_eq!(get(1, "command").as_deref(), Some("SET"));
+        assert_eq!(get(1, "commands").as_deref(), Some("2"));
+        assert_eq!(get(1, "peer").as_deref(), Some("127.0.0.1:6379"));
+        assert_eq!(get(1, "outcome").as_deref(), Some("timeout"));
+        assert_eq!(get(1, "db"), None);
+    }
+}
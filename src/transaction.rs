@@ -0,0 +1,142 @@
+use super::cmd::{self, commands::Append, commands::Commands, Command};
+use super::codec::{Request, Response};
+use super::errors::CommandError;
+
+/// Transaction result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionResult<T> {
+    /// Transaction is executed, contains output of each queued command
+    Committed(T),
+    /// Transaction is aborted because one of the watched keys got modified
+    Aborted,
+}
+
+impl<T> TransactionResult<T> {
+    /// Returns true if transaction is executed
+    pub fn is_committed(&self) -> bool {
+        matches!(self, TransactionResult::Committed(_))
+    }
+
+    /// Converts transaction result to `Option`
+    pub fn committed(self) -> Option<T> {
+        match self {
+            TransactionResult::Committed(val) => Some(val),
+            TransactionResult::Aborted => None,
+        }
+    }
+}
+
+/// Redis transaction
+///
+/// Transaction wraps queued commands with `MULTI` and `EXEC` and sends
+/// them in one write, so it is safe to execute transaction on a shared
+/// connection.
+///
+/// ```rust
+/// use ntex_redis::{cmd, RedisConnector, Transaction, TransactionResult};
+/// # use rand::{thread_rng, Rng, distributions::Alphanumeric};
+/// # fn gen_random_key() -> String {
+/// #    thread_rng().sample_iter(&Alphanumeric).take(12).map(char::from).collect::<String>()
+/// # }
+///
+/// #[ntex::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let redis = RedisConnector::new("127.0.0.1:6379").connect().await?;
+///     let key = gen_random_key();
+///
+///     let tx = Transaction::new()
+///         .add(cmd::Set(&key, "1"))
+///         .add(cmd::IncrBy(&key, 10));
+///
+///     if let TransactionResult::Committed((set, incr)) = redis.transaction(tx).await? {
+///         assert!(set?);
+///         assert_eq!(incr?, 11);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub struct Transaction<T> {
+    cmds: T,
+}
+
+impl Transaction<()> {
+    /// Create new transaction
+    pub fn new() -> Self {
+        Transaction { cmds: () }
+    }
+}
+
+impl Default for Transaction<()> {
+    fn default() -> Self {
+        Transaction::new()
+    }
+}
+
+impl<T> Transaction<T> {
+    #[allow(clippy::should_implement_trait)]
+    /// Queue command
+    pub fn add<C>(self, cmd: C) -> Transaction<T::Output>
+    where
+        C: Command,
+        T: Append<C>,
+    {
+        Transaction {
+            cmds: self.cmds.append(cmd),
+        }
+    }
+}
+
+impl<C: Command> Transaction<Vec<C>> {
+    /// Queue command of the same type
+    pub fn push(&mut self, cmd: C) {
+        self.cmds.push(cmd);
+    }
+}
+
+impl<C: Command> From<Vec<C>> for Transaction<Vec<C>> {
+    fn from(cmds: Vec<C>) -> Self {
+        Transaction { cmds }
+    }
+}
+
+impl<C: Command> std::iter::FromIterator<C> for Transaction<Vec<C>> {
+    fn from_iter<I: IntoIterator<Item = C>>(iter: I) -> Self {
+        Transaction {
+            cmds: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T: Commands> Transaction<T> {
+    /// `MULTI`, queued commands and `EXEC`
+    pub(crate) fn into_requests(self) -> Vec<Request> {
+        let mut reqs = vec![cmd::Multi().to_request()];
+        self.cmds.to_requests(&mut reqs);
+        reqs.push(cmd::Exec().to_request());
+        reqs
+    }
+
+    /// Decode responses for `MULTI`, queued commands and `EXEC`
+    pub(crate) fn to_output(
+        items: Vec<Response>,
+    ) -> Result<TransactionResult<T::Output>, CommandError> {
+        let mut items = items.into_iter();
+
+        // MULTI and QUEUED replies
+        let exec = items.next_back();
+        for item in &mut items {
+            if let Response::Error(err) = item {
+                return Err(CommandError::Error(err));
+            }
+        }
+
+        let exec = exec.ok_or(CommandError::Output("Missing response", Response::Nil))?;
+        match cmd::commands::ExecCommand::to_output(exec.into_result()?)? {
+            Some(items) => Ok(TransactionResult::Committed(T::to_output(
+                &mut items.into_iter(),
+            ))),
+            None => Ok(TransactionResult::Aborted),
+        }
+    }
+}
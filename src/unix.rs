@@ -0,0 +1,41 @@
+use std::{future::Future, pin::Pin};
+
+use ntex::connect::{net::unix_connect, Connect, ConnectError};
+use ntex::{io::Io, service::Service};
+
+use super::RedisConnector;
+
+/// Unix domain socket connector
+///
+/// Connect address is used as a socket path.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct UnixConnector;
+
+impl Service<Connect<String>> for UnixConnector {
+    type Response = Io;
+    type Error = ConnectError;
+    type Future<'f> = Pin<Box<dyn Future<Output = Result<Io, ConnectError>>>>;
+
+    fn call(&self, req: Connect<String>) -> Self::Future<'_> {
+        let path = req.get_ref().clone();
+        Box::pin(async move { Ok(unix_connect(path).await?) })
+    }
+}
+
+impl RedisConnector<String, ()> {
+    /// Create redis connector for unix domain socket
+    ///
+    /// ```rust,no_run
+    /// use ntex_redis::{cmd, RedisConnector};
+    ///
+    /// #[ntex::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let redis = RedisConnector::unix("/var/run/redis.sock").connect().await?;
+    ///     redis.exec(cmd::Ping()).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn unix<P: AsRef<str>>(path: P) -> RedisConnector<String, UnixConnector> {
+        RedisConnector::new(path.as_ref().to_string()).connector(UnixConnector)
+    }
+}
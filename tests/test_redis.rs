@@ -1,7 +1,12 @@
-use ntex::util::{Bytes, HashMap};
-use ntex_redis::{cmd, Client, RedisConnector};
+use ntex::{service::Service, time::sleep, time::Millis, util::Bytes, util::HashMap};
+use ntex_redis::{array, cmd, codec::Response, Client, Pipeline, RedisConnector, RedisPool};
+use ntex_redis::{codec::Codec, errors::CommandError, errors::ConnectError, errors::Error};
+use ntex_redis::{Backoff, Credentials, ReconnectingClient, RetryPolicy};
+use ntex_redis::{CacheConfig, CachedClient, PubSubManager, Transaction, TransactionResult};
+use ntex_redis::{CommandEvent, Observer};
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use std::time::{Duration, SystemTime};
+use std::{cell::RefCell, convert::TryFrom, rc::Rc};
 
 async fn connect() -> Client {
     RedisConnector::new("127.0.0.1:6379")
@@ -36,6 +41,54 @@ async fn test_auth_simple() {
     assert!(result.is_err());
 }
 
+#[ntex::test]
+async fn test_acl_auth() {
+    let admin = connect().await;
+    let user = new_key();
+    admin
+        .call(array![
+            "ACL", "SETUSER", &user, "on", ">secret", "~*", "+@all"
+        ])
+        .await
+        .unwrap();
+
+    for resp3 in [false, true] {
+        let mut connector = RedisConnector::new("127.0.0.1:6379")
+            .username(&user)
+            .password("secret")
+            .client_name("acl-test");
+        if resp3 {
+            connector = connector.resp3();
+        }
+        let redis = connector.connect().await.unwrap();
+        assert_eq!(redis.is_resp3(), resp3);
+        assert_eq!(redis.exec(cmd::Ping()).await.unwrap(), "PONG");
+
+        // first rejected password is skipped
+        let redis = RedisConnector::new("127.0.0.1:6379")
+            .username(&user)
+            .password("wrong")
+            .password("secret")
+            .connect_simple()
+            .await
+            .unwrap();
+        assert_eq!(redis.exec(cmd::Ping()).await.unwrap(), "PONG");
+
+        let mut connector = RedisConnector::new("127.0.0.1:6379")
+            .username(&user)
+            .password("wrong");
+        if resp3 {
+            connector = connector.resp3();
+        }
+        match connector.connect().await {
+            Err(ConnectError::Auth(err)) => assert!(err.starts_with("WRONGPASS")),
+            _ => panic!("authentication error is expected"),
+        }
+    }
+
+    admin.call(array!["ACL", "DELUSER", &user]).await.unwrap();
+}
+
 #[ntex::test]
 async fn test_strings() {
     env_logger::init();
@@ -347,3 +400,899 @@ async fn test_psubscribe() {
         cmd::SubscribeItem::UnSubscribed(pattern.clone())
     );
 }
+
+#[ntex::test]
+async fn test_pool() {
+    let pool = RedisPool::new(RedisConnector::new("127.0.0.1:6379"))
+        .min_size(2)
+        .max_size(4);
+    pool.warm_up().await.unwrap();
+    assert_eq!(pool.size(), 2);
+
+    let key = new_key();
+    let result = pool.exec(cmd::Set(&key, "value")).await.unwrap();
+    assert!(result);
+
+    let futs: Vec<_> = (0..16).map(|_| pool.exec(cmd::Get(&key))).collect();
+    for res in ntex::util::join_all(futs).await {
+        assert_eq!(res.unwrap().unwrap(), "value");
+    }
+    assert!(pool.size() <= 4);
+
+    let result = pool.exec(cmd::Del(&key)).await.unwrap();
+    assert_eq!(result, 1);
+}
+
+#[ntex::test]
+async fn test_reconnect() {
+    let redis = ReconnectingClient::new(RedisConnector::new("127.0.0.1:6379").database(1))
+        .backoff(Backoff::new(Millis(10), Millis(100)))
+        .retry(RetryPolicy::Retry(1));
+    let key = new_key();
+    let result = redis.exec(cmd::Set(&key, "value")).await.unwrap();
+    assert!(result);
+
+    let id = redis.call(array!["CLIENT", "ID"]).await.unwrap();
+    let id = i64::try_from(id).unwrap().to_string();
+    let killer = connect().await;
+    killer
+        .call(array!["CLIENT", "KILL", "ID", id])
+        .await
+        .unwrap();
+    sleep(Millis(100)).await;
+
+    let resp = redis.exec(cmd::Get(&key)).await.unwrap().unwrap();
+    assert_eq!(resp, "value");
+    assert!(redis.is_connected());
+}
+
+#[ntex::test]
+async fn test_pipeline() {
+    let redis = connect().await;
+    let key = new_key();
+
+    let (set, incr, get) = redis
+        .pipeline(
+            Pipeline::new()
+                .add(cmd::Set(&key, "value"))
+                .add(cmd::IncrBy(&key, 10))
+                .add(cmd::Get(&key)),
+        )
+        .await
+        .unwrap();
+    assert!(set.unwrap());
+    assert!(matches!(incr, Err(CommandError::Error(_))));
+    assert_eq!(get.unwrap().unwrap(), "value");
+
+    let key = new_key();
+    let pipeline: Pipeline<_> = (0..500).map(|_| cmd::IncrBy(&key, 1)).collect();
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .connect_simple()
+        .await
+        .unwrap();
+    let result = redis.pipeline(pipeline).await.unwrap();
+    assert_eq!(result.len(), 500);
+    assert_eq!(*result[499].as_ref().unwrap(), 500);
+
+    redis.pipeline(Pipeline::new()).await.unwrap();
+}
+
+#[ntex::test]
+async fn test_transaction() {
+    let redis = connect().await;
+    let key = new_key();
+
+    let result = redis
+        .transaction(
+            Transaction::new()
+                .add(cmd::Set(&key, "value"))
+                .add(cmd::IncrBy(&key, 10))
+                .add(cmd::Get(&key)),
+        )
+        .await
+        .unwrap();
+    let (set, incr, get) = result.committed().unwrap();
+    assert!(set.unwrap());
+    assert!(matches!(incr, Err(CommandError::Error(_))));
+    assert_eq!(get.unwrap().unwrap(), "value");
+
+    // watched key is modified
+    let simple = RedisConnector::new("127.0.0.1:6379")
+        .connect_simple()
+        .await
+        .unwrap();
+    assert!(simple.exec(cmd::Watch(&key)).await.unwrap());
+    redis.exec(cmd::Set(&key, "other")).await.unwrap();
+    let result = simple
+        .transaction(Transaction::new().add(cmd::Set(&key, "1")))
+        .await
+        .unwrap();
+    assert!(matches!(result, TransactionResult::Aborted));
+    let resp = redis.exec(cmd::Get(&key)).await.unwrap().unwrap();
+    assert_eq!(resp, "other");
+}
+
+#[ntex::test]
+async fn test_watch() {
+    let key = new_key();
+    let other = connect().await;
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .connect_simple()
+        .await
+        .unwrap();
+
+    // first attempt is aborted by concurrent write
+    let mut attempts = 0;
+    let result = redis
+        .watch(vec![&key], 1, || {
+            attempts += 1;
+            let attempt = attempts;
+            let (key, redis, other) = (&key, &redis, &other);
+            async move {
+                let value = redis.exec(cmd::Get(key)).await?;
+                if attempt == 1 {
+                    other.exec(cmd::Set(key, "other")).await?;
+                }
+                assert_eq!(value.is_some(), attempt > 1);
+                Ok(Transaction::new().add(cmd::Set(key, "value")))
+            }
+        })
+        .await
+        .unwrap();
+    assert!(result.is_committed());
+    assert_eq!(attempts, 2);
+    let resp = other.exec(cmd::Get(&key)).await.unwrap().unwrap();
+    assert_eq!(resp, "value");
+
+    // shared client
+    let shared = other.clone();
+    let result = shared
+        .watch(vec![&key], 1, || async {
+            Ok(Transaction::new().add(cmd::Del(&key)))
+        })
+        .await;
+    assert!(matches!(result, Err(CommandError::SharedConnection)));
+
+    // dedicated connection
+    let pool = RedisPool::new(RedisConnector::new("127.0.0.1:6379"));
+    let dedicated = pool.dedicated().await.unwrap();
+    let result = dedicated
+        .watch(vec![&key], 0, || async {
+            Ok(Transaction::new().add(cmd::Del(&key)))
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.committed().unwrap().0.unwrap(), 1);
+}
+
+#[ntex::test]
+async fn test_watch_unwatch() {
+    use ntex::service::fn_service;
+    use ntex_redis::server::{ClientCommand, RedisServer};
+    use std::sync::{Arc, Mutex};
+
+    // server fails queued commands
+    let commands = Arc::new(Mutex::new(Vec::new()));
+    let commands2 = commands.clone();
+    let srv = ntex::server::test_server(move || {
+        let commands = commands2.clone();
+        RedisServer::new(fn_service(move |cmd: ClientCommand| {
+            let name = String::from_utf8_lossy(cmd.name()).to_uppercase();
+            commands.lock().unwrap().push(name.clone());
+            async move {
+                Ok::<_, ()>(if name == "SET" {
+                    Response::Error("ERR test".into())
+                } else {
+                    Response::String("OK".into())
+                })
+            }
+        }))
+    });
+
+    // connection is not dedicated
+    let connector = RedisConnector::new(srv.addr().to_string());
+    let result = connector
+        .connect()
+        .await
+        .unwrap()
+        .watch(vec!["key"], 0, || async {
+            Ok(Transaction::new().add(cmd::Set("key", "value")))
+        })
+        .await;
+    assert!(matches!(result, Err(CommandError::SharedConnection)));
+    assert!(commands.lock().unwrap().is_empty());
+
+    // keys are unwatched if transaction fails
+    let pool = RedisPool::new(connector);
+    let result = pool
+        .dedicated()
+        .await
+        .unwrap()
+        .watch(vec!["key"], 0, || async {
+            Ok(Transaction::new().add(cmd::Set("key", "value")))
+        })
+        .await;
+    assert!(matches!(result, Err(CommandError::Error(_))));
+    assert_eq!(
+        &*commands.lock().unwrap(),
+        &["WATCH", "MULTI", "SET", "EXEC", "UNWATCH"]
+    );
+}
+
+struct BlPop(String);
+
+impl cmd::Command for BlPop {
+    type Output = ntex_redis::codec::Response;
+
+    fn to_request(self) -> ntex_redis::codec::Request {
+        array!["BLPOP", self.0, "1"]
+    }
+
+    fn to_output(val: Self::Output) -> Result<Self::Output, CommandError> {
+        Ok(val)
+    }
+}
+
+#[ntex::test]
+async fn test_timeout() {
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .timeout(Millis(100))
+        .connect()
+        .await
+        .unwrap();
+    let key = new_key();
+    redis.exec(cmd::Set(&key, "value")).await.unwrap();
+
+    let result = redis.exec(BlPop(new_key())).await;
+    assert!(matches!(result, Err(CommandError::Timeout)));
+
+    // late response for BLPOP is dropped
+    let resp = redis
+        .exec_timeout(cmd::Get(&key), Millis(2_000))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(resp, "value");
+}
+
+#[ntex::test]
+async fn test_resp3() {
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .resp3()
+        .connect()
+        .await
+        .unwrap();
+    let key = new_key();
+
+    redis.exec(cmd::HSet(&key, "field", "value")).await.unwrap();
+    let map = redis.exec(cmd::HGetAll(&key)).await.unwrap();
+    assert_eq!(map[&Bytes::from_static(b"field")], "value");
+
+    let value = redis.exec(cmd::Get(new_key())).await.unwrap();
+    assert!(value.is_none());
+
+    let props = redis.exec(cmd::Hello(3)).await.unwrap();
+    assert_eq!(props["proto"], Response::Integer(3));
+}
+
+#[ntex::test]
+async fn test_client_cache() {
+    let connector = RedisConnector::new("127.0.0.1:6379");
+    let redis = CachedClient::connect(&connector, CacheConfig::new())
+        .await
+        .unwrap();
+    let other = connect().await;
+    let key = new_key();
+
+    other.exec(cmd::Set(&key, "1")).await.unwrap();
+    assert_eq!(redis.get(&key).await.unwrap().unwrap(), "1");
+    assert_eq!(redis.get(&key).await.unwrap().unwrap(), "1");
+    assert_eq!(redis.stats().hits, 1);
+
+    other.exec(cmd::Set(&key, "2")).await.unwrap();
+    sleep(Millis(100)).await;
+    assert_eq!(redis.get(&key).await.unwrap().unwrap(), "2");
+    assert_eq!(redis.stats().invalidations, 1);
+}
+
+#[ntex::test]
+async fn test_client_subscribe() {
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .resp3()
+        .connect()
+        .await
+        .unwrap();
+    let channel = new_key();
+
+    let subscription = redis.subscribe(cmd::Subscribe(vec![&channel])).unwrap();
+    assert_eq!(
+        subscription.recv().await.unwrap().unwrap(),
+        cmd::SubscribeItem::Subscribed(Bytes::from(channel.clone()))
+    );
+
+    // shared connection keeps serving commands
+    let key = new_key();
+    redis.exec(cmd::Set(&key, "value")).await.unwrap();
+    assert_eq!(redis.exec(cmd::Get(&key)).await.unwrap().unwrap(), "value");
+
+    let publisher = connect().await;
+    publisher
+        .exec(cmd::Publish(&channel, "hello"))
+        .await
+        .unwrap();
+    assert_eq!(
+        subscription.recv().await.unwrap().unwrap(),
+        cmd::SubscribeItem::Message {
+            pattern: None,
+            channel: Bytes::from(channel.clone()),
+            payload: Bytes::from_static(b"hello")
+        }
+    );
+
+    drop(subscription);
+    sleep(Millis(100)).await;
+    let receivers = publisher
+        .exec(cmd::Publish(&channel, "hello"))
+        .await
+        .unwrap();
+    assert_eq!(receivers, 0);
+
+    let redis = connect().await;
+    assert!(matches!(
+        redis.subscribe(cmd::Subscribe(vec![&channel])),
+        Err(CommandError::Resp3Required)
+    ));
+}
+
+#[ntex::test]
+async fn test_pubsub_manager() {
+    let manager = PubSubManager::new(RedisConnector::new("127.0.0.1:6379"));
+    let channel = new_key();
+
+    let first = manager.subscribe(&channel);
+    let second = manager.subscribe(&channel);
+    let pattern = manager.psubscribe(format!("{}*", channel));
+    sleep(Millis(100)).await;
+    assert!(manager.is_connected());
+
+    let publisher = connect().await;
+    let receivers = publisher
+        .exec(cmd::Publish(&channel, "hello"))
+        .await
+        .unwrap();
+    assert_eq!(receivers, 2);
+
+    assert_eq!(first.recv().await.unwrap().payload, "hello");
+    assert_eq!(second.recv().await.unwrap().payload, "hello");
+    let msg = pattern.recv().await.unwrap();
+    assert_eq!(msg.channel, channel);
+    assert_eq!(msg.pattern.unwrap(), format!("{}*", channel));
+
+    // channel stays subscribed until last stream is dropped
+    drop(first);
+    drop(pattern);
+    sleep(Millis(100)).await;
+    let receivers = publisher
+        .exec(cmd::Publish(&channel, "hello"))
+        .await
+        .unwrap();
+    assert_eq!(receivers, 1);
+    assert_eq!(second.recv().await.unwrap().payload, "hello");
+
+    drop(second);
+    sleep(Millis(100)).await;
+    let receivers = publisher
+        .exec(cmd::Publish(&channel, "hello"))
+        .await
+        .unwrap();
+    assert_eq!(receivers, 0);
+}
+
+#[ntex::test]
+async fn test_from_url() {
+    let redis = RedisConnector::from_url("redis://127.0.0.1:6379/1?protocol=3&client_name=test")
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    assert!(redis.is_resp3());
+
+    let key = new_key();
+    redis.exec(cmd::Set(&key, "value")).await.unwrap();
+
+    // key is stored in database 1
+    let db0 = connect().await;
+    assert_eq!(db0.exec(cmd::Get(&key)).await.unwrap(), None);
+    assert_eq!(redis.exec(cmd::Get(&key)).await.unwrap().unwrap(), "value");
+
+    assert!(RedisConnector::from_url("http://127.0.0.1:6379").is_err());
+}
+
+#[cfg(unix)]
+#[ntex::test]
+async fn test_unix_socket() {
+    // redis-server --port 0 --unixsocket /tmp/redis.sock
+    let redis = RedisConnector::unix("/tmp/redis.sock")
+        .database(1)
+        .connect()
+        .await
+        .unwrap();
+    let key = new_key();
+    redis.exec(cmd::Set(&key, "value")).await.unwrap();
+    assert_eq!(redis.exec(cmd::Get(&key)).await.unwrap().unwrap(), "value");
+
+    let simple = RedisConnector::from_url("redis+unix:///tmp/redis.sock?db=1")
+        .unwrap()
+        .connect_simple()
+        .await
+        .unwrap();
+    assert_eq!(simple.exec(cmd::Get(&key)).await.unwrap().unwrap(), "value");
+}
+
+#[ntex::test]
+async fn test_credentials_provider() {
+    let admin = connect().await;
+    let user = new_key();
+    admin
+        .call(array![
+            "ACL", "SETUSER", &user, "on", ">token-1", "~*", "+@all"
+        ])
+        .await
+        .unwrap();
+
+    let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+    let calls2 = calls.clone();
+    let user2 = user.clone();
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .credentials_provider(move || {
+            calls2.set(calls2.get() + 1);
+            let creds = Credentials::new(format!("token-{}", calls2.get()))
+                .username(&user2)
+                .expires_in(Millis(500));
+            async move { Ok(creds) }
+        })
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(calls.get(), 1);
+
+    // rotate token, client re-authenticates before expiration
+    admin
+        .call(array!["ACL", "SETUSER", &user, ">token-2"])
+        .await
+        .unwrap();
+    let key = new_key();
+    for _ in 0..10 {
+        redis.exec(cmd::IncrBy(&key, 1)).await.unwrap();
+        sleep(Millis(50)).await;
+    }
+    assert!(calls.get() >= 2);
+    assert_eq!(redis.exec(cmd::IncrBy(&key, 0)).await.unwrap(), 10);
+
+    admin.call(array!["ACL", "DELUSER", &user]).await.unwrap();
+}
+
+#[ntex::test]
+async fn test_on_connect() {
+    let key = new_key();
+    let key2 = key.clone();
+    let connector = RedisConnector::new("127.0.0.1:6379")
+        .database(1)
+        .client_name("hook-test")
+        .on_connect(move |client| {
+            let key = key2.clone();
+            Box::pin(async move {
+                client.exec(cmd::IncrBy(key, 1)).await?;
+                Ok(())
+            })
+        });
+    let redis = ReconnectingClient::new(connector)
+        .backoff(Backoff::new(Millis(10), Millis(100)))
+        .retry(RetryPolicy::Retry(1));
+
+    // hook runs before first command
+    assert_eq!(redis.exec(cmd::IncrBy(&key, 0)).await.unwrap(), 1);
+    let name = redis.call(array!["CLIENT", "GETNAME"]).await.unwrap();
+    assert_eq!(Bytes::try_from(name).unwrap(), "hook-test");
+
+    // hook is replayed after reconnect
+    let id = redis.call(array!["CLIENT", "ID"]).await.unwrap();
+    let id = i64::try_from(id).unwrap().to_string();
+    let killer = connect().await;
+    killer
+        .call(array!["CLIENT", "KILL", "ID", id])
+        .await
+        .unwrap();
+    sleep(Millis(100)).await;
+    assert_eq!(redis.exec(cmd::IncrBy(&key, 0)).await.unwrap(), 2);
+}
+
+#[ntex::test]
+async fn test_keepalive() {
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .keepalive(Millis(50))
+        .keepalive_timeout(Millis(500))
+        .connect()
+        .await
+        .unwrap();
+    assert!(redis.is_healthy());
+    redis.health_check(Millis(500)).await.unwrap();
+
+    let id = redis.call(array!["CLIENT", "ID"]).await.unwrap();
+    let id = i64::try_from(id).unwrap().to_string();
+    sleep(Millis(200)).await;
+
+    // idle connection is pinged in background
+    let info = connect()
+        .await
+        .call(array!["CLIENT", "LIST", "ID", id])
+        .await
+        .unwrap();
+    let info = Bytes::try_from(info).unwrap();
+    assert!(std::str::from_utf8(&info).unwrap().contains("cmd=ping"));
+    assert!(redis.is_healthy());
+}
+
+#[ntex::test]
+async fn test_pool_connect_error() {
+    use ntex::service::fn_service;
+    use ntex_redis::server::{ClientCommand, RedisServer};
+    use std::cell::Cell;
+
+    let srv = ntex::server::test_server(|| {
+        RedisServer::new(fn_service(|cmd: ClientCommand| async move {
+            if cmd.is("HANG") {
+                std::future::pending::<()>().await;
+            }
+            Ok::<_, ()>(Response::String("PONG".into()))
+        }))
+    });
+
+    // only first connection succeeds
+    let connects = Rc::new(Cell::new(0));
+    let connects2 = connects.clone();
+    let connector = RedisConnector::new(srv.addr().to_string()).on_connect(move |_| {
+        connects2.set(connects2.get() + 1);
+        let refused = connects2.get() > 1;
+        Box::pin(async move {
+            if refused {
+                Err(ConnectError::Unauthorized)
+            } else {
+                Ok(())
+            }
+        })
+    });
+    let pool = RedisPool::new(connector).max_size(2);
+    let client = pool.get().await.unwrap();
+    let hang = client.call(array!["HANG"]);
+
+    // busy connection is used if new one cannot be opened
+    let _client = pool.get().await.unwrap();
+    assert_eq!(connects.get(), 2);
+    assert_eq!(pool.size(), 1);
+    drop(hang);
+}
+
+#[ntex::test]
+async fn test_keepalive_busy() {
+    use ntex::service::fn_service;
+    use ntex_redis::server::{ClientCommand, RedisServer};
+
+    // server replies to SLOW command after a delay
+    let srv = ntex::server::test_server(|| {
+        RedisServer::new(fn_service(|cmd: ClientCommand| async move {
+            if cmd.is("SLOW") {
+                sleep(Millis(300)).await;
+            }
+            Ok::<_, ()>(Response::String("PONG".into()))
+        }))
+    });
+
+    let redis = RedisConnector::new(srv.addr().to_string())
+        .keepalive(Millis(50))
+        .keepalive_timeout(Millis(50))
+        .connect()
+        .await
+        .unwrap();
+    redis.exec(cmd::Ping()).await.unwrap();
+
+    // long-running command outlives keepalive interval and timeout
+    let res = redis.call(array!["SLOW"]).await;
+    assert_eq!(res.unwrap(), Response::String("PONG".into()));
+    assert!(redis.is_healthy());
+}
+
+#[derive(Clone, Default)]
+struct Events(Rc<RefCell<Vec<String>>>);
+
+impl Observer for Events {
+    fn command(&self, ev: &CommandEvent<'_>) {
+        assert!(ev.bytes_written > 0);
+        assert!(ev.bytes_read > 0);
+        self.0.borrow_mut().push(format!(
+            "{}:{}:{}",
+            ev.name,
+            ev.commands,
+            ev.outcome.as_str()
+        ));
+    }
+
+    fn connected(&self, address: &str) {
+        self.0.borrow_mut().push(format!("connected:{}", address));
+    }
+
+    fn disconnected(&self, address: &str) {
+        self.0
+            .borrow_mut()
+            .push(format!("disconnected:{}", address));
+    }
+}
+
+#[ntex::test]
+async fn test_observer() {
+    let events = Events::default();
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .observer(events.clone())
+        .connect()
+        .await
+        .unwrap();
+    let key = new_key();
+
+    redis.exec(cmd::Set(&key, "value")).await.unwrap();
+    assert!(redis.exec(cmd::LPush(&key, "value")).await.is_err());
+    let (value, _) = redis
+        .pipeline(Pipeline::new().add(cmd::Get(&key)).add(cmd::Del(&key)))
+        .await
+        .unwrap();
+    assert_eq!(value.unwrap().unwrap(), "value");
+
+    let id = redis.call(array!["CLIENT", "ID"]).await.unwrap();
+    let id = i64::try_from(id).unwrap().to_string();
+    connect()
+        .await
+        .call(array!["CLIENT", "KILL", "ID", id])
+        .await
+        .unwrap();
+    sleep(Millis(100)).await;
+
+    assert_eq!(
+        &*events.0.borrow(),
+        &[
+            "connected:127.0.0.1:6379",
+            "SET:1:success",
+            "LPUSH:1:server_error",
+            "GET:2:success",
+            "CLIENT:1:success",
+            "disconnected:127.0.0.1:6379",
+        ]
+    );
+}
+
+#[ntex::test]
+async fn test_observer_call() {
+    use ntex::service::fn_service;
+    use ntex_redis::server::{ClientCommand, RedisServer};
+
+    let srv = ntex::server::test_server(|| {
+        RedisServer::new(fn_service(|cmd: ClientCommand| async move {
+            if cmd.is("SLOW") {
+                sleep(Millis(200)).await;
+            }
+            Ok::<_, ()>(Response::String("OK".into()))
+        }))
+    });
+
+    let events = Events::default();
+    let redis = RedisConnector::new(srv.addr().to_string())
+        .timeout(Millis(50))
+        .observer(events.clone())
+        .connect()
+        .await
+        .unwrap();
+
+    // default timeout is not applied to raw requests
+    let res = redis.call(array!["SLOW"]).await;
+    assert_eq!(res.unwrap(), Response::String("OK".into()));
+    assert_eq!(&events.0.borrow()[1..], &["SLOW:1:success"]);
+}
+
+#[ntex::test]
+async fn test_log_wire() {
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .password("test")
+        .log_wire()
+        .connect_simple()
+        .await;
+    assert!(redis.is_err());
+
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .log_wire()
+        .connect()
+        .await
+        .unwrap();
+    let key = new_key();
+    redis.exec(cmd::Set(&key, "a\r\nb")).await.unwrap();
+    assert_eq!(redis.exec(cmd::Get(&key)).await.unwrap().unwrap(), "a\r\nb");
+}
+
+#[ntex::test]
+async fn test_exec_stream() {
+    use ntex_redis::codec::ReplyChunk;
+
+    let redis = RedisConnector::new("127.0.0.1:6379")
+        .connect_simple()
+        .await
+        .unwrap();
+    let key = new_key();
+    let value = (0..4 * 1024 * 1024)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<_>>();
+    redis.exec(cmd::Set(&key, value.clone())).await.unwrap();
+
+    let reply = redis.exec_stream(cmd::Get(&key)).unwrap();
+    let mut data = Vec::new();
+    let mut chunks = 0;
+    while let Some(chunk) = reply.recv().await {
+        match chunk.unwrap() {
+            ReplyChunk::Bytes(chunk) => data.extend_from_slice(&chunk),
+            item => panic!("Unexpected item: {:?}", item),
+        }
+        chunks += 1;
+    }
+    drop(reply);
+    assert!(chunks > 1);
+    assert!(data == value);
+
+    // connection is usable after stream
+    let hash = new_key();
+    redis.exec(cmd::HSet(&hash, "a", "b")).await.unwrap();
+    let reply = redis.exec_stream(cmd::HGetAll(&hash)).unwrap();
+    let mut items = Vec::new();
+    while let Some(item) = reply.recv().await {
+        items.push(item.unwrap());
+    }
+    drop(reply);
+    assert_eq!(
+        items,
+        vec![
+            ReplyChunk::Element(Response::Bytes(Bytes::from_static(b"a"))),
+            ReplyChunk::Element(Response::Bytes(Bytes::from_static(b"b"))),
+        ]
+    );
+
+    // missing key and server errors
+    let reply = redis.exec_stream(cmd::Get(new_key())).unwrap();
+    assert_eq!(
+        reply.recv().await.unwrap().unwrap(),
+        ReplyChunk::Element(Response::Nil)
+    );
+    assert!(reply.recv().await.is_none());
+    drop(reply);
+    let reply = redis.exec_stream(cmd::LPush(&key, "a")).unwrap();
+    assert!(matches!(
+        reply.recv().await,
+        Some(Err(CommandError::Error(_)))
+    ));
+    assert!(reply.recv().await.is_none());
+    drop(reply);
+    assert!(redis.exec(cmd::Del(&key)).await.is_ok());
+
+    // dropped stream closes connection
+    let reply = redis.exec_stream(cmd::HGetAll(&hash)).unwrap();
+    drop(reply);
+    assert!(redis.exec(cmd::Ping()).await.is_err());
+}
+
+#[ntex::test]
+async fn test_decoder_limits() {
+    let key = new_key();
+    let redis = connect().await;
+    redis.exec(cmd::Set(&key, "x".repeat(1024))).await.unwrap();
+
+    let limited = RedisConnector::new("127.0.0.1:6379")
+        .codec(Codec::new().max_bulk_size(512))
+        .connect()
+        .await
+        .unwrap();
+    match limited.exec(cmd::Get(&key)).await {
+        Err(CommandError::Protocol(Error::Limit(_))) => (),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    sleep(Millis(100)).await;
+    assert!(!limited.is_connected());
+
+    // simple client
+    let hash = new_key();
+    redis
+        .exec(cmd::HSet(&hash, "field1", "1").entry("field2", "2"))
+        .await
+        .unwrap();
+    let simple = RedisConnector::new("127.0.0.1:6379")
+        .codec(Codec::new().max_array_len(2))
+        .connect_simple()
+        .await
+        .unwrap();
+    match simple.exec(cmd::HGetAll(&hash)).await {
+        Err(CommandError::Protocol(Error::Limit(_))) => (),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+}
+
+#[ntex::test]
+async fn test_server() {
+    use ntex::service::{fn_factory, fn_service};
+    use ntex_redis::server::{ClientCommand, RedisServer};
+
+    let srv = ntex::server::test_server(|| {
+        RedisServer::new(fn_factory(|| async {
+            // connection state
+            let store = Rc::new(RefCell::new(HashMap::default()));
+            Ok::<_, ()>(fn_service(move |cmd: ClientCommand| {
+                let store = store.clone();
+                async move {
+                    let args = cmd.args();
+                    Ok::<_, ()>(if cmd.is("PING") {
+                        Response::String("PONG".into())
+                    } else if cmd.is("HELLO") {
+                        Response::Map(vec![(
+                            Response::String("proto".into()),
+                            Response::Integer(3),
+                        )])
+                    } else if cmd.is("SET") && args.len() == 2 {
+                        store.borrow_mut().insert(args[0].clone(), args[1].clone());
+                        Response::String("OK".into())
+                    } else if cmd.is("GET") && args.len() == 1 {
+                        store
+                            .borrow()
+                            .get(&args[0])
+                            .cloned()
+                            .map(Response::Bytes)
+                            .unwrap_or(Response::Nil)
+                    } else {
+                        Response::Error("ERR unknown command".into())
+                    })
+                }
+            }))
+        }))
+    });
+
+    let redis = RedisConnector::new(srv.addr().to_string())
+        .connect()
+        .await
+        .unwrap();
+    redis.exec(cmd::Ping()).await.unwrap();
+    redis.exec(cmd::Set("key", "value")).await.unwrap();
+    assert_eq!(
+        redis.exec(cmd::Get("key")).await.unwrap().unwrap(),
+        Bytes::from_static(b"value")
+    );
+    assert_eq!(redis.exec(cmd::Get("unknown")).await.unwrap(), None);
+    match redis.exec(cmd::Del("key")).await {
+        Err(CommandError::Error(err)) => assert_eq!(err, "ERR unknown command"),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+
+    // pipelined commands are replied in order
+    let results = ntex::util::join_all((0..10).map(|_| redis.exec(cmd::Ping()))).await;
+    assert!(results.into_iter().all(|res| res.is_ok()));
+
+    // protocol is switched with HELLO
+    let redis = RedisConnector::new(srv.addr().to_string())
+        .resp3()
+        .connect()
+        .await
+        .unwrap();
+    assert!(redis.is_resp3());
+    assert_eq!(redis.exec(cmd::Get("key")).await.unwrap(), None);
+
+    // inline commands, connection is closed on protocol error
+    use std::io::{Read, Write};
+    let mut stream = std::net::TcpStream::connect(srv.addr()).unwrap();
+    stream.write_all(b"PING\r\n*1\r\n:1\r\n").unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    assert_eq!(
+        reply,
+        "+PONG\r\n-ERR Protocol error: Expected bulk string argument\r\n"
+    );
+}